@@ -0,0 +1,100 @@
+//! 每次按键更新屏幕的开销
+//!
+//! [`typeing::tui::TypeingTui`] 只能写入真正的终端，所以这里按照它写入的内容生成同样的转义序列：
+//! 增量更新只移动到刚输入的字符、重写这个字符，再把光标移回原处；全部重绘则清空屏幕，
+//! 重新显示所有行。除了生成的时间，通过 SSH 使用时写入的字节数同样重要，所以也一起打印出来
+
+use std::fmt::Write;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use termion::{clear, cursor};
+use typeing::theme::Theme;
+use typeing::tui::{layout_words, Text};
+
+/// 一页的单词数，与默认的单词数相同
+const NUM_WORDS: usize = 50;
+/// 每行最多的列数，相当于 200 列终端的 40%
+const LINE_WIDTH: u16 = 80;
+/// 模拟已经输入了一页中的多少个字符
+const TYPED_CHARS: usize = 120;
+
+fn page() -> Vec<String> {
+    let words: Vec<String> = [
+        "the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog",
+    ]
+    .iter()
+    .cycle()
+    .take(NUM_WORDS)
+    .map(|word| word.to_string())
+    .collect();
+    layout_words(&words, LINE_WIDTH, 12, false)
+}
+
+/// 只重写刚输入的字符，与 `TypeingTui::replace_text` 写入的内容相同
+fn incremental(out: &mut String, theme: &Theme, typed: char, pos: (u16, u16)) {
+    write!(
+        out,
+        "{}{}{}",
+        cursor::Goto(pos.0, pos.1),
+        theme.correct(Text::from(typed)),
+        cursor::Goto(pos.0 + 1, pos.1)
+    )
+    .unwrap();
+}
+
+/// 清空屏幕后重新显示所有行，已经输入的字符和没有输入的字符使用不同的颜色
+fn full_redraw(out: &mut String, theme: &Theme, lines: &[String], typed_chars: usize) {
+    write!(out, "{}", clear::All).unwrap();
+    let mut index = 0;
+    for (line_no, line) in lines.iter().enumerate() {
+        write!(out, "{}", cursor::Goto(1, line_no as u16 + 1)).unwrap();
+        for c in line.chars() {
+            let text = if index < typed_chars {
+                theme.correct(Text::from(c))
+            } else {
+                theme.untyped(Text::from(c))
+            };
+            write!(out, "{}", text).unwrap();
+            index += 1;
+        }
+    }
+    let (x, y) = (typed_chars as u16 % LINE_WIDTH + 1, 1);
+    write!(out, "{}", cursor::Goto(x, y)).unwrap();
+}
+
+fn keystroke(c: &mut Criterion) {
+    let theme = Theme::default();
+    let lines = page();
+
+    let mut out = String::new();
+    incremental(&mut out, &theme, 'o', (40, 12));
+    let incremental_bytes = out.len();
+    out.clear();
+    full_redraw(&mut out, &theme, &lines, TYPED_CHARS);
+    println!(
+        "每次按键写入的字节数: 增量更新 {}，全部重绘 {}",
+        incremental_bytes,
+        out.len()
+    );
+
+    let mut group = c.benchmark_group("keystroke_redraw");
+    group.bench_function("incremental", |b| {
+        b.iter(|| {
+            out.clear();
+            incremental(&mut out, &theme, black_box('o'), black_box((40, 12)));
+            out.len()
+        })
+    });
+    group.bench_function("full_redraw", |b| {
+        b.iter(|| {
+            out.clear();
+            full_redraw(&mut out, &theme, black_box(&lines), TYPED_CHARS);
+            out.len()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, keystroke);
+criterion_main!(benches);
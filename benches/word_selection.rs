@@ -0,0 +1,56 @@
+//! 选择单词的吞吐量
+//!
+//! 比较每次选择单词时重新解析整个单词列表（以前的做法）与创建时解析一次、
+//! 之后只选择位置的 [`RawWordSelector`]。计时模式的测试会反复补充单词，
+//! 所以每次选择的开销会累积起来
+
+use std::hint::black_box;
+use std::str::FromStr;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use typeing::textgen::{RawWordSelector, WordSelector};
+
+/// 每次补充的单词数，与计时模式一页的单词数相当
+const BATCH: usize = 50;
+
+/// 与内置的 top10000 大小相当的单词列表，每行一个由 2 到 4 个字母组成的单词
+fn word_list() -> String {
+    (0..10_000)
+        .map(|mut n| {
+            let mut word = String::from("a");
+            loop {
+                word.push((b'a' + (n % 26) as u8) as char);
+                n /= 26;
+                if n == 0 {
+                    break;
+                }
+            }
+            word + "\n"
+        })
+        .collect()
+}
+
+fn selection(c: &mut Criterion) {
+    let contents = word_list();
+    let mut group = c.benchmark_group("word_selection");
+    group.throughput(Throughput::Elements(BATCH as u64));
+
+    group.bench_function("reparse_each_batch", |b| {
+        b.iter(|| {
+            let mut selector = RawWordSelector::from_str(black_box(&contents))
+                .unwrap()
+                .with_seed(1);
+            selector.new_words(BATCH).unwrap()
+        })
+    });
+
+    let mut selector = RawWordSelector::from_str(&contents).unwrap().with_seed(1);
+    group.bench_function("parsed_pool", |b| {
+        b.iter(|| selector.new_words(BATCH).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, selection);
+criterion_main!(benches);
@@ -1,18 +1,865 @@
-use clap::Parser;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+use serde::{Deserialize, Deserializer};
+
+use termion::color::AnsiValue;
+
+use crate::keymap::{FinishKey, Layout};
+use crate::theme::{Theme, ThemePreset, UntypedStyle};
+use crate::tui::{CursorStyle, DEFAULT_LINE_WIDTH_PCT, DEFAULT_MAX_WORDS_PER_LINE};
+use crate::wordlists::{BuiltInWordlist, Language, QuoteLength};
+use crate::TypeingError;
 
 const CLI_HELP: &str = "一个值得信赖的终端打字测试器
 
 快捷键:
 ctrl-c: 退出
 ctrl-r: 用一组新单词重新开始测试
-删除最后一个单词
+ctrl-t: 用同一组单词重新开始测试
+ctrl-w: 删除最后一个单词
+esc: 提前结束测试，显示已经输入的部分的结果
+
+结果页面:
+n: 用一组新单词开始下一次测试
+r: 用同一组单词重试
+q: 退出
 ";
 
+/// 每次测试最多的单词数，更多的单词没有意义，还会让生成单词和排列行变得很慢
+pub const MAX_NUM_WORDS: usize = 100_000;
+
+/// 测试结果的输出格式
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// 适合阅读的文本
+    Text,
+    /// 一行 JSON
+    Json,
+}
+
+/// 输入错误时的提示方式
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnError {
+    /// 没有提示
+    None,
+    /// 响铃
+    Bell,
+    /// 短暂地反转输错的字符的颜色
+    Flash,
+}
+
+/// 结果中速度的单位
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Metric {
+    /// 每分钟单词数，每 5 个字符算作一个单词
+    Wpm,
+    /// 每分钟输入正确的字符数
+    Cpm,
+}
+
+/// 结果页面显示的内容
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResultsLevel {
+    /// 只显示速度和准确率
+    Summary,
+    /// 还显示原始速度、净速度、一致性、最长连续正确、种子、最慢的按键和输错的单词
+    Detailed,
+}
+
 /// 主要配置
-#[derive(Parser)]
+#[derive(Parser, Deserialize)]
 #[clap(author, version, about=CLI_HELP)]
+// 也可以从配置文件反序列化，没有写出的选项使用与命令行相同的默认值。
+// 这里不用文档注释，以免出现在 `--help` 中
+#[serde(default, deny_unknown_fields)]
 pub struct TypeingConfig {
+    /// 单词列表名称。可以重复使用来同时从多个单词列表中选择单词。
+    #[clap(value_enum, short, long, default_values_t = [BuiltInWordlist::Top250])]
+    #[serde(deserialize_with = "one_or_many")]
+    pub wordlist: Vec<BuiltInWordlist>,
+
+    /// 单词的语言。英语以外的语言使用这种语言内置的单词列表，不能与 --wordlist 一起使用。
+    #[clap(
+        value_enum,
+        long,
+        default_value_t = Language::English,
+        conflicts_with_all = ["wordlist", "wordlist_file", "stdin", "text_file", "quote"]
+    )]
+    pub language: Language,
+
+    /// 允许同一个单词连续出现两次。默认会重新选择与上一个单词相同的单词。
+    #[clap(long)]
+    pub allow_repeats: bool,
+
+    /// 从多个单词列表中选择单词时，每个列表的权重，用逗号分隔。默认所有列表的权重相同。
+    #[clap(long, value_name = "WEIGHTS", value_delimiter = ',')]
+    #[serde(deserialize_with = "one_or_many")]
+    pub wordlist_weights: Vec<u32>,
+
+    /// 按照单词在列表中的排名加权选择，越常用的单词出现得越多。默认所有单词的概率相同。
+    #[clap(long, conflicts_with = "text_file")]
+    pub weighted: bool,
+
+    /// 自定义单词列表文件的路径。
+    #[clap(short = 'f', long = "file", conflicts_with = "wordlist")]
+    pub wordlist_file: Option<String>,
+
+    /// 把这个文件中的单词添加到选中的内置单词列表中，例如 `--wordlist top1000 --append-wordlist my-words.txt`。
+    /// 选择了多个内置单词列表时，添加到每一个列表中。重复的单词会更经常出现。
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["wordlist_file", "stdin", "text_file", "quote"]
+    )]
+    pub append_wordlist: Option<String>,
+
+    /// 自由输入：没有需要输入的文本，接受任何字符，按 ctrl-d 结束并按照输入的字符数计算速度。
+    #[clap(long)]
+    pub zen: bool,
+
+    /// 只练习历史记录中测试结束时仍然输错的单词。
+    #[clap(long, conflicts_with_all = ["wordlist", "wordlist_file", "stdin", "text_file", "quote"])]
+    pub drill_errors: bool,
+
+    /// 练习 N 个字母（2 或 3）的常见英语字母组合，例如 `th` 和 `ing`，而不是单词。
+    #[clap(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(2..=3),
+        conflicts_with_all = ["wordlist", "wordlist_file", "stdin", "text_file", "quote", "drill_errors"]
+    )]
+    pub ngram: Option<u8>,
+
+    /// 从标准输入读取单词列表，例如 `echo "foo bar" | typeing --stdin`。
+    /// 标准输入会在进入终端界面之前全部读完，按键总是从终端读取。
+    #[clap(long, conflicts_with_all = ["wordlist", "wordlist_file"])]
+    pub stdin: bool,
+
+    /// 按原样练习的文本文件的路径，保留单词的顺序、标点和大小写。
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["wordlist", "wordlist_file", "stdin"])]
+    pub text_file: Option<String>,
+
+    /// 练习一句内置的名言，而不是随机的单词。
+    #[clap(
+        long,
+        conflicts_with_all = ["wordlist", "wordlist_file", "stdin", "text_file", "weighted", "punctuation", "numbers", "capitals"]
+    )]
+    pub quote: bool,
+
+    /// 只选择这个长度的名言。默认可以选择任何长度的名言。
+    #[clap(value_enum, long, value_name = "LENGTH", requires = "quote")]
+    pub quote_length: Option<QuoteLength>,
 
     /// 在每个测试中显示的单词数。
+    #[clap(short, long, default_value_t = 30)]
     pub num_words: usize,
-}
\ No newline at end of file
+
+    /// 每行单词最多占终端宽度的百分比（10 到 100）。
+    #[clap(
+        long,
+        value_name = "PERCENT",
+        default_value_t = DEFAULT_LINE_WIDTH_PCT,
+        value_parser = clap::value_parser!(u16).range(10..=100)
+    )]
+    pub line_width_pct: u16,
+
+    /// 每行最多的单词数。
+    #[clap(long, value_name = "N", default_value_t = DEFAULT_MAX_WORDS_PER_LINE)]
+    pub max_words_per_line: usize,
+
+    /// 每行固定显示 N 个单词，不管终端有多宽，同时忽略 --line-width-pct 和 --max-words-per-line。
+    /// 和 --seed 一起使用时，每次的分行都一样，便于截图。
+    #[clap(long, value_name = "N")]
+    pub words_per_line: Option<usize>,
+
+    /// 把比一行更长的单词断开，分到多行显示。默认这样的单词单独占一行。
+    #[clap(long)]
+    pub break_long_words: bool,
+
+    /// 颜色主题。
+    #[clap(value_enum, long, default_value_t = ThemePreset::Default)]
+    pub theme: ThemePreset,
+
+    /// 测试中光标的形状。
+    #[clap(value_enum, long, value_name = "STYLE", default_value_t = CursorStyle::BlinkingBar)]
+    pub cursor: CursorStyle,
+
+    /// 从终端的左上角开始显示单词和结果，不在终端中间居中显示。
+    #[clap(long)]
+    pub minimal: bool,
+
+    /// 用鼠标点击已经输入的单词时，删除到这个单词的开头重新输入。
+    /// 启用之后终端通常不能再用鼠标选择文本。
+    #[clap(long)]
+    pub mouse: bool,
+
+    /// 反转显示正在输入的单词中还没有输入的字符，更容易看清楚输入到了哪里。
+    #[clap(long)]
+    pub highlight_word: bool,
+
+    /// 在光标所在的字符上显示反转颜色的方块，并隐藏终端的光标。适合终端的光标不容易看清的情况。
+    #[clap(long)]
+    pub block_caret: bool,
+
+    /// 光标一次移动多个字符时（例如 ctrl-w）平滑地移动，而不是直接跳过去。
+    #[clap(long)]
+    pub smooth_caret: bool,
+
+    /// 输入正确的字符的颜色（256 色调色板中的编号），覆盖主题中的颜色。
+    #[clap(long, value_name = "COLOR")]
+    pub correct_color: Option<u8>,
+
+    /// 输入错误的字符的颜色（256 色调色板中的编号），覆盖主题中的颜色。
+    #[clap(long, value_name = "COLOR")]
+    pub incorrect_color: Option<u8>,
+
+    /// 还没有输入的文本的颜色（256 色调色板中的编号），覆盖主题中的颜色。
+    #[clap(long, value_name = "COLOR")]
+    pub untyped_color: Option<u8>,
+
+    /// 还没有输入的文本的样式，覆盖主题中的样式。终端不能正确显示模糊样式时可以使用 dim-color 或 none。
+    #[clap(value_enum, long, value_name = "STYLE")]
+    pub untyped_style: Option<UntypedStyle>,
+
+    /// 光标的颜色（256 色调色板中的编号），覆盖主题中的颜色。
+    #[clap(long, value_name = "COLOR")]
+    pub cursor_color: Option<u8>,
+
+    /// 计时模式的秒数。设置后测试会一直进行到时间用完，而不是输入完所有单词。
+    #[clap(short, long, value_name = "SECONDS")]
+    pub time: Option<u64>,
+
+    /// 连续进行这么多次测试，每次完成之后自动用新单词开始下一次，最后显示平均速度、最好速度和平均准确率。
+    #[clap(long, value_name = "N")]
+    pub repeat: Option<usize>,
+
+    /// 开始测试之前倒计时的秒数。倒计时结束之前的按键会被忽略，计时从倒计时结束时开始。
+    #[clap(long, value_name = "SECONDS")]
+    pub countdown: Option<u64>,
+
+    /// 在单词中加入标点和大写字母。
+    #[clap(short, long, conflicts_with = "text_file")]
+    pub punctuation: bool,
+
+    /// 在单词中混入数字。
+    #[clap(long, conflicts_with = "text_file")]
+    pub numbers: bool,
+
+    /// 随机把这个比例（0 到 1 之间）的单词首字母大写。
+    #[clap(long, value_name = "RATIO", value_parser = parse_ratio, conflicts_with = "text_file")]
+    pub capitals: Option<f64>,
+
+    /// 盲打模式：输入时不显示是否正确，在结果页面中列出输错的单词。
+    #[clap(long)]
+    pub blind: bool,
+
+    /// 允许用退格键和 ctrl-w 修改已经正确输入的单词。默认不能删除最后一个正确输入的单词和它之前的字符。
+    #[clap(long)]
+    pub free_backspace: bool,
+
+    /// 练习的键盘布局。操作系统使用 QWERTY 布局时，把按下的按键当作这个布局中同一个位置的字符。
+    #[clap(value_enum, long, value_name = "NAME", default_value_t = Layout::Qwerty)]
+    pub layout: Layout,
+
+    /// 忽略大小写：输入 `A` 也算作正确地输入了 `a`，但是仍然显示实际输入的字符。默认区分大小写。
+    #[clap(long)]
+    pub case_insensitive: bool,
+
+    /// 在最后一个单词后面也需要输入一个空格才能完成测试，与其他单词保持一致。
+    /// 默认输入最后一个单词的最后一个字符时测试就结束了。
+    #[clap(long)]
+    pub require_final_space: bool,
+
+    /// 严格模式：只有在当前单词完全正确地输入之后才能按空格，否则空格算作一次错误的按键，光标不会移动。
+    #[clap(long)]
+    pub strict: bool,
+
+    /// 输错时停下：只有输入了正确的字符光标才会前进，输错的按键仍然计入准确率。
+    #[clap(long)]
+    pub stop_on_error: bool,
+
+    /// 这么多秒没有按键时自动暂停计时，按下任意键继续。暂停的时间不计入测试的时间。
+    #[clap(long, value_name = "SECONDS")]
+    pub idle_timeout: Option<u64>,
+
+    /// 提前结束测试的按键。提前结束时只统计已经输入完的单词，结果仍然会显示和保存。
+    #[clap(value_enum, long, value_name = "KEY", default_value_t = FinishKey::Esc)]
+    pub finish_key: FinishKey,
+
+    /// 没有修正的错误（输错的字符和多余字符）达到这么多个时提前结束测试，显示结果。0 表示不限制。
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    pub max_errors: usize,
+
+    /// 同一个字符输错这么多次之后，跳过当前单词，剩下的字符都算作输错。0 表示从不跳过。
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    pub hide_live_errors_after: usize,
+
+    /// 结果中速度的单位。
+    #[clap(value_enum, long, value_name = "METRIC", default_value_t = Metric::Wpm)]
+    pub metric: Metric,
+
+    /// 结果页面显示的内容。
+    #[clap(value_enum, long, value_name = "LEVEL", default_value_t = ResultsLevel::Summary)]
+    pub results: ResultsLevel,
+
+    /// 输入错误时的提示方式。
+    #[clap(value_enum, long, value_name = "MODE", default_value_t = OnError::None)]
+    pub on_error: OnError,
+
+    /// 选择单词使用的随机数种子。相同的种子会得到相同的单词。
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// 退出之后把最后一次完成的测试结果以这个格式打印到标准输出。
+    #[clap(value_enum, long, value_name = "FORMAT")]
+    pub output: Option<OutputFormat>,
+
+    /// 把生成的单词用空格分隔打印到标准输出，而不是开始测试。与 --seed 一起使用时得到的单词与测试中相同。
+    #[clap(long)]
+    pub print_words: bool,
+
+    /// 打印版本、启用的特性和每个嵌入的单词列表的哈希，而不是开始测试。用于报告问题。
+    #[clap(long, hide = true)]
+    pub debug_info: bool,
+
+    /// 显示历史记录中最好的和平均的速度，而不是开始测试。
+    #[clap(long)]
+    pub stats: bool,
+
+    /// 把最后一次完成的测试的 JSON 格式结果追加到这个文件，每行一个结果，便于脚本收集。
+    /// 与历史记录无关，多个 typeing 同时写入同一个文件也是安全的。
+    #[clap(long, value_name = "PATH")]
+    pub append_result: Option<String>,
+
+    /// 把历史记录导出为 CSV 文件，而不是开始测试。
+    #[clap(long, value_name = "PATH")]
+    pub export_csv: Option<String>,
+}
+
+impl Default for TypeingConfig {
+    /// 与不带任何命令行参数运行时相同的配置
+    fn default() -> Self {
+        Self::parse_from(["typeing"])
+    }
+}
+
+impl TypeingConfig {
+    /// 读取配置文件，然后用命令行参数覆盖其中的选项
+    ///
+    /// 配置文件位于 `$XDG_CONFIG_HOME/typeing/config.toml`
+    /// （没有设置 `$XDG_CONFIG_HOME` 时为 `~/.config/typeing/config.toml`），
+    /// 是一个 TOML 文件，选项名与结构体的字段名相同，例如：
+    ///
+    /// ```toml
+    /// num_words = 50
+    /// wordlist = "top1000"
+    /// punctuation = true
+    /// ```
+    ///
+    /// 配置文件中的值会作为命令行参数的默认值，所以命令行参数总是优先。
+    /// 配置文件不存在时等同于 [`TypeingConfig::parse`]
+    pub fn load() -> Result<Self, TypeingError> {
+        let mut command = Self::command();
+
+        if let Some(path) = config_file_path() {
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+                Err(err) => {
+                    return Err(TypeingError::from(format!(
+                        "无法读取配置文件 {}: {}",
+                        path.display(),
+                        err
+                    )))
+                }
+            };
+
+            for (key, values) in parse_config_file(&contents)
+                .map_err(|msg| TypeingError::from(format!("{}: {}", path.display(), msg)))?
+            {
+                command = command.mut_arg(key, |arg| arg.default_values(values));
+            }
+        }
+
+        let matches = command.get_matches();
+        Ok(Self::from_arg_matches(&matches).unwrap_or_else(|err| err.exit()))
+    }
+
+    /// 不解析命令行参数，在代码中创建配置
+    ///
+    /// 没有设置的选项使用与命令行相同的默认值，也不会读取配置文件
+    pub fn builder() -> TypeingConfigBuilder {
+        TypeingConfigBuilder {
+            config: Self::default(),
+        }
+    }
+
+    /// 检查选项的取值和组合是否有效
+    ///
+    /// 命令行参数解析时已经检查了大部分互相冲突的选项，这里再检查一次，
+    /// 以便用 [`TypeingConfig::builder`] 创建的配置也得到同样的检查
+    pub fn validate(&self) -> Result<(), TypeingError> {
+        if self.num_words == 0 {
+            return Err(TypeingError::from("单词数必须大于 0".to_string()));
+        }
+        if self.num_words > MAX_NUM_WORDS {
+            return Err(TypeingError::from(format!(
+                "单词数不能超过 {}，得到 {}",
+                MAX_NUM_WORDS, self.num_words
+            )));
+        }
+        if self.idle_timeout == Some(0) {
+            return Err(TypeingError::from("空闲时间必须大于 0 秒".to_string()));
+        }
+        if self.repeat == Some(0) {
+            return Err(TypeingError::from("重复次数必须大于 0".to_string()));
+        }
+        if self.max_words_per_line == 0 {
+            return Err(TypeingError::from("每行的单词数必须大于 0".to_string()));
+        }
+        if self.words_per_line == Some(0) {
+            return Err(TypeingError::from("每行的单词数必须大于 0".to_string()));
+        }
+        if let Some(ratio) = self.capitals {
+            parse_ratio(&ratio.to_string()).map_err(TypeingError::from)?;
+        }
+
+        // 单词的来源只能有一个
+        let sources: Vec<&str> = [
+            (self.quote, "--quote"),
+            (self.text_file.is_some(), "--text-file"),
+            (self.stdin, "--stdin"),
+            (self.drill_errors, "--drill-errors"),
+            (self.ngram.is_some(), "--ngram"),
+            (self.wordlist_file.is_some(), "--file"),
+            (self.language != Language::English, "--language"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect();
+        if sources.len() > 1 {
+            return Err(TypeingError::from(format!(
+                "{} 不能一起使用",
+                sources.join(" 和 ")
+            )));
+        }
+
+        // 只能把单词添加到内置的单词列表中
+        if self.append_wordlist.is_some() {
+            if let Some(source) = sources.iter().find(|&&source| source != "--language") {
+                return Err(TypeingError::from(format!(
+                    "--append-wordlist 不能与 {} 一起使用",
+                    source
+                )));
+            }
+        }
+
+        // 名言按原样显示，不能修改其中的单词
+        if self.quote {
+            let modifiers: Vec<&str> = [
+                (self.punctuation, "--punctuation"),
+                (self.numbers, "--numbers"),
+                (self.capitals.is_some(), "--capitals"),
+                (self.weighted, "--weighted"),
+            ]
+            .into_iter()
+            .filter_map(|(set, name)| set.then_some(name))
+            .collect();
+            if !modifiers.is_empty() {
+                return Err(TypeingError::from(format!(
+                    "--quote 不能与 {} 一起使用",
+                    modifiers.join("、")
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 选择的主题，以及覆盖主题的颜色
+    pub fn color_theme(&self) -> Theme {
+        let mut theme = self.theme.theme();
+        if let Some(color) = self.correct_color {
+            theme.correct = AnsiValue(color);
+        }
+        if let Some(color) = self.incorrect_color {
+            theme.incorrect = AnsiValue(color);
+        }
+        if let Some(color) = self.untyped_color {
+            theme.untyped = Some(AnsiValue(color));
+            theme.untyped_style = UntypedStyle::DimColor;
+        }
+        if let Some(style) = self.untyped_style {
+            theme.untyped_style = style;
+        }
+        if let Some(color) = self.cursor_color {
+            theme.cursor = Some(AnsiValue(color));
+        }
+
+        theme
+    }
+
+    /// 正在使用的单词列表或者文本文件的名称
+    pub fn text_name(&self) -> String {
+        if self.zen {
+            "自由输入".to_string()
+        } else if self.quote {
+            match self
+                .quote_length
+                .and_then(|length| length.to_possible_value())
+            {
+                Some(length) => format!("名言 ({})", length.get_name()),
+                None => "名言".to_string(),
+            }
+        } else if let Some(text_file) = &self.text_file {
+            format!("文本文件 `{}`", text_file)
+        } else if self.stdin {
+            "标准输入".to_string()
+        } else if self.drill_errors {
+            "输错过的单词".to_string()
+        } else if let Some(length) = self.ngram {
+            format!("{} 个字母的组合", length)
+        } else if let Some(wordlist_file) = &self.wordlist_file {
+            format!("自定义文件 `{}`", wordlist_file)
+        } else {
+            let builtin = if self.language != Language::English {
+                self.language
+                    .to_possible_value()
+                    .map_or_else(String::new, |value| value.get_name().to_string())
+            } else {
+                self.wordlist
+                    .iter()
+                    .filter_map(|wordlist| wordlist.to_possible_value())
+                    .map(|value| value.get_name().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" + ")
+            };
+            match &self.append_wordlist {
+                Some(path) => format!("{} + 自定义文件 `{}`", builtin, path),
+                None => builtin,
+            }
+        }
+    }
+}
+
+/// [`TypeingConfig`] 的构造器，参见 [`TypeingConfig::builder`]
+pub struct TypeingConfigBuilder {
+    config: TypeingConfig,
+}
+
+impl TypeingConfigBuilder {
+    /// 从这些内置单词列表中选择单词
+    pub fn wordlist(mut self, wordlist: Vec<BuiltInWordlist>) -> Self {
+        self.config.wordlist = wordlist;
+        self
+    }
+
+    /// 单词的语言
+    pub fn language(mut self, language: Language) -> Self {
+        self.config.language = language;
+        self
+    }
+
+    /// 把这个文件中的单词添加到选中的内置单词列表中
+    pub fn append_wordlist(mut self, path: impl Into<String>) -> Self {
+        self.config.append_wordlist = Some(path.into());
+        self
+    }
+
+    /// 自定义单词列表文件的路径
+    pub fn wordlist_file(mut self, path: impl Into<String>) -> Self {
+        self.config.wordlist_file = Some(path.into());
+        self
+    }
+
+    /// 按原样练习的文本文件的路径
+    pub fn text_file(mut self, path: impl Into<String>) -> Self {
+        self.config.text_file = Some(path.into());
+        self
+    }
+
+    /// 练习内置的名言
+    pub fn quote(mut self, quote: bool) -> Self {
+        self.config.quote = quote;
+        self
+    }
+
+    /// 练习 `length` 个字母的组合，而不是单词
+    pub fn ngram(mut self, length: u8) -> Self {
+        self.config.ngram = Some(length);
+        self
+    }
+
+    /// 每次测试的单词数
+    pub fn num_words(mut self, num_words: usize) -> Self {
+        self.config.num_words = num_words;
+        self
+    }
+
+    /// 选择单词使用的随机数种子
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    /// 计时模式的秒数
+    pub fn time(mut self, seconds: u64) -> Self {
+        self.config.time = Some(seconds);
+        self
+    }
+
+    /// 在单词中加入标点和大写字母
+    pub fn punctuation(mut self, punctuation: bool) -> Self {
+        self.config.punctuation = punctuation;
+        self
+    }
+
+    /// 在单词中混入数字
+    pub fn numbers(mut self, numbers: bool) -> Self {
+        self.config.numbers = numbers;
+        self
+    }
+
+    /// 随机把这个比例的单词首字母大写
+    pub fn capitals(mut self, ratio: f64) -> Self {
+        self.config.capitals = Some(ratio);
+        self
+    }
+
+    /// 盲打模式
+    pub fn blind(mut self, blind: bool) -> Self {
+        self.config.blind = blind;
+        self
+    }
+
+    /// 允许修改已经正确输入的单词
+    pub fn free_backspace(mut self, free_backspace: bool) -> Self {
+        self.config.free_backspace = free_backspace;
+        self
+    }
+
+    /// 严格模式
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.config.strict = strict;
+        self
+    }
+
+    /// 最后一个单词后面也要输入一个空格才结束测试
+    pub fn require_final_space(mut self, require_final_space: bool) -> Self {
+        self.config.require_final_space = require_final_space;
+        self
+    }
+
+    /// 输错时停下，直到输入正确的字符
+    pub fn stop_on_error(mut self, stop_on_error: bool) -> Self {
+        self.config.stop_on_error = stop_on_error;
+        self
+    }
+
+    /// 提前结束测试的按键
+    pub fn finish_key(mut self, finish_key: FinishKey) -> Self {
+        self.config.finish_key = finish_key;
+        self
+    }
+
+    /// 没有修正的错误达到这么多个时结束测试，0 表示不限制
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.config.max_errors = max_errors;
+        self
+    }
+
+    /// 同一个字符输错这么多次时跳过这个单词，0 表示不跳过
+    pub fn hide_live_errors_after(mut self, attempts: usize) -> Self {
+        self.config.hide_live_errors_after = attempts;
+        self
+    }
+
+    /// 忽略大小写
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.config.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// 用 [`TypeingConfig::validate`] 检查选项并创建配置
+    pub fn build(self) -> Result<TypeingConfig, TypeingError> {
+        self.config.validate()?;
+
+        Ok(self.config)
+    }
+}
+
+/// 解析 0 到 1 之间的比例
+fn parse_ratio(value: &str) -> Result<f64, String> {
+    let ratio: f64 = value
+        .parse()
+        .map_err(|_| format!("`{}` 不是一个数字", value))?;
+
+    if (0.0..=1.0).contains(&ratio) {
+        Ok(ratio)
+    } else {
+        Err(format!("比例必须在 0 到 1 之间，得到 {}", ratio))
+    }
+}
+
+/// 保存配置文件和历史记录的目录
+///
+/// 即 `$XDG_CONFIG_HOME/typeing`，没有设置 `$XDG_CONFIG_HOME` 时为 `~/.config/typeing`
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_home.join("typeing"))
+}
+
+/// 配置文件的路径
+fn config_file_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("config.toml"))
+}
+
+/// 解析配置文件，返回每个选项的名称和作为命令行参数默认值的字符串
+///
+/// 文件先按照 [`TypeingConfig`] 的字段反序列化，所以未知的选项和类型错误的值都会在这里报告。
+/// 键中的 `-` 会被转换为 `_`，以便与字段名对应。
+fn parse_config_file(contents: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let table: toml::Table = contents
+        .parse()
+        .map_err(|err: toml::de::Error| err.to_string())?;
+    let table: toml::Table = table
+        .into_iter()
+        .map(|(key, value)| (key.replace('-', "_"), value))
+        .collect();
+
+    toml::Value::Table(table.clone())
+        .try_into::<TypeingConfig>()
+        .map_err(|err| err.to_string())?;
+
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let values = match value {
+                toml::Value::Array(items) => items
+                    .into_iter()
+                    .map(config_value)
+                    .collect::<Option<Vec<String>>>(),
+                value => config_value(value).map(|value| vec![value]),
+            }
+            .ok_or_else(|| format!("选项 `{}` 的值无效", key))?;
+
+            Ok((key, values))
+        })
+        .collect()
+}
+
+/// 把一个字符串、数字或者布尔值转换为命令行参数的写法
+fn config_value(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(string) => Some(string),
+        toml::Value::Integer(integer) => Some(integer.to_string()),
+        toml::Value::Float(float) => Some(float.to_string()),
+        toml::Value::Boolean(boolean) => Some(boolean.to_string()),
+        _ => None,
+    }
+}
+
+/// 配置文件中可以重复的选项既可以写一个值，也可以写一个数组
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    #[test]
+    fn quoted_strings_keep_commas_and_escaped_quotes() {
+        let options = parse_config_file(
+            r#"
+            # 注释
+            text-file = "a, \"quoted\" # file.txt"
+            wordlist = ["top250", "top1000"]
+            num_words = 50
+            capitals = 0.5
+            punctuation = true
+            "#,
+        )
+        .unwrap();
+
+        let value = |key: &str| {
+            options
+                .iter()
+                .find(|(option, _)| option == key)
+                .map(|(_, values)| values.clone())
+                .unwrap()
+        };
+        assert_eq!(value("text_file"), ["a, \"quoted\" # file.txt"]);
+        assert_eq!(value("wordlist"), ["top250", "top1000"]);
+        assert_eq!(value("num_words"), ["50"]);
+        assert_eq!(value("capitals"), ["0.5"]);
+        assert_eq!(value("punctuation"), ["true"]);
+    }
+
+    #[test]
+    fn repeatable_options_accept_a_single_value() {
+        let options = parse_config_file("wordlist = \"os\"").unwrap();
+        assert_eq!(options, [("wordlist".to_string(), vec!["os".to_string()])]);
+    }
+
+    #[test]
+    fn unknown_options_and_wrong_types_are_rejected() {
+        assert!(parse_config_file("no_such_option = 1").is_err());
+        assert!(parse_config_file("num_words = \"many\"").is_err());
+        assert!(parse_config_file("wordlist = \"top7\"").is_err());
+        assert!(parse_config_file("num_words = ").is_err());
+    }
+
+    /// 配置文件中枚举的写法必须与命令行参数相同
+    fn assert_serde_names_match_clap<T: ValueEnum + DeserializeOwned + PartialEq>() {
+        for variant in T::value_variants() {
+            let name = variant.to_possible_value().unwrap().get_name().to_string();
+            let parsed: T = toml::Value::String(name.clone()).try_into().unwrap();
+            assert!(parsed == *variant, "`{}`", name);
+        }
+    }
+
+    #[test]
+    fn enum_names_match_command_line() {
+        assert_serde_names_match_clap::<OutputFormat>();
+        assert_serde_names_match_clap::<OnError>();
+        assert_serde_names_match_clap::<Metric>();
+        assert_serde_names_match_clap::<ResultsLevel>();
+        assert_serde_names_match_clap::<BuiltInWordlist>();
+        assert_serde_names_match_clap::<Language>();
+        assert_serde_names_match_clap::<QuoteLength>();
+        assert_serde_names_match_clap::<UntypedStyle>();
+        assert_serde_names_match_clap::<ThemePreset>();
+        assert_serde_names_match_clap::<CursorStyle>();
+        assert_serde_names_match_clap::<Layout>();
+        assert_serde_names_match_clap::<FinishKey>();
+    }
+}
@@ -0,0 +1,361 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::stats::TypingStats;
+
+/// 历史记录文件中的一条测试结果
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResultRecord {
+    /// 完成测试的时间（Unix 时间戳，秒）
+    pub timestamp: u64,
+    pub wpm: f64,
+    /// 原始 WPM，较早的记录中没有保存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_wpm: Option<f64>,
+    pub accuracy: f64,
+    /// 单词列表或者文本文件的名称
+    pub wordlist: String,
+    pub num_words: usize,
+    /// 测试结束时仍然输错的单词，较早的记录中没有保存
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missed_words: Vec<String>,
+}
+
+/// 历史记录文件的路径
+///
+/// 每行是一条 JSON 格式的 [`ResultRecord`]
+pub fn history_file_path() -> Option<PathBuf> {
+    Some(config::config_dir()?.join("history.jsonl"))
+}
+
+/// 把一次测试的结果追加到历史记录文件
+///
+/// `missed_words` 是测试结束时仍然输错的单词，用于 `--drill-errors`
+pub fn append_result(stats: &TypingStats, missed_words: &[String]) -> io::Result<()> {
+    let path = history_file_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "找不到配置目录"))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let record = ResultRecord {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_secs()),
+        wpm: stats.wpm(),
+        raw_wpm: Some(stats.raw_wpm()),
+        accuracy: stats.accuracy(),
+        wordlist: stats.text_name().to_string(),
+        num_words: stats.num_words(),
+        missed_words: missed_words.to_vec(),
+    };
+
+    let line = serde_json::to_string(&record)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+/// 读取历史记录
+///
+/// 文件不存在或者无法读取时返回空的历史记录，损坏的行会被跳过
+pub fn load_history() -> Vec<ResultRecord> {
+    let contents = match history_file_path().map(fs::read_to_string) {
+        Some(Ok(contents)) => contents,
+        _ => return Vec::new(),
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// 显示速度百分位需要的最少历史记录数，更少的记录得到的百分位没有意义
+pub const MIN_PERCENTILE_RUNS: usize = 5;
+
+/// 之前所有测试的结果
+pub struct TypingHistory {
+    records: Vec<ResultRecord>,
+}
+
+impl TypingHistory {
+    pub fn new(records: Vec<ResultRecord>) -> Self {
+        Self { records }
+    }
+
+    /// 读取历史记录文件，参见 [`load_history`]
+    pub fn load() -> Self {
+        Self::new(load_history())
+    }
+
+    pub fn records(&self) -> &[ResultRecord] {
+        &self.records
+    }
+
+    /// 速度 `wpm` 超过了历史记录中百分之多少的测试
+    ///
+    /// 记录少于 [`MIN_PERCENTILE_RUNS`] 条时返回 `None`
+    pub fn percentile(&self, wpm: f64) -> Option<f64> {
+        if self.records.len() < MIN_PERCENTILE_RUNS {
+            return None;
+        }
+
+        let slower = self
+            .records
+            .iter()
+            .filter(|record| record.wpm < wpm)
+            .count();
+        Some(slower as f64 / self.records.len() as f64 * 100.0)
+    }
+}
+
+/// 历史记录中所有输错过的单词，每个单词只出现一次
+///
+/// 单词保持保存时的样子，包括大小写和标点，这样练习的就是当时输错的单词
+pub fn missed_words() -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for record in load_history() {
+        for word in &record.missed_words {
+            let word = word.trim();
+            if !word.is_empty() && !words.iter().any(|missed| missed == word) {
+                words.push(word.to_string());
+            }
+        }
+    }
+
+    words
+}
+
+/// 等待另一个进程释放锁文件时每次重试的间隔
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+/// 等待锁文件的最多重试次数，之后认为锁文件是崩溃的进程留下的
+const LOCK_RETRIES: u32 = 500;
+
+/// 用 `create_new` 创建的锁文件，释放时删除
+struct FileLock(PathBuf);
+
+impl FileLock {
+    fn acquire(path: PathBuf) -> io::Result<Self> {
+        for _ in 0..LOCK_RETRIES {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self(path)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    std::thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "等待锁文件 {} 超时，如果没有其他 typeing 正在写入，可以删除它",
+                path.display()
+            ),
+        ))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// 在 `path` 后面加上后缀，例如 `results.jsonl` 加上 `.lock` 得到 `results.jsonl.lock`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// 把一行 JSON 格式的测试结果追加到 `path`，用于 `--append-result`
+///
+/// 与历史记录不同，这个文件由用户的脚本读取。写入时先用 `<path>.lock` 锁文件
+/// 等待其他同时写入的进程，然后把原来的内容和新的一行写入 `<path>.tmp`，再重命名为 `path`，
+/// 所以读取的脚本总是看到完整的行
+pub fn append_result_line(path: &Path, line: &str) -> io::Result<()> {
+    let _lock = FileLock::acquire(with_suffix(path, ".lock"))?;
+
+    let mut contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    if contents.last().is_some_and(|&last| last != b'\n') {
+        contents.push(b'\n');
+    }
+    contents.extend_from_slice(line.as_bytes());
+    contents.push(b'\n');
+
+    let temp_path = with_suffix(path, ".tmp");
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)
+}
+
+/// CSV 的表头，与 [`ResultRecord::csv_fields`] 中的列一一对应
+const CSV_HEADER: [&str; 6] = [
+    "timestamp",
+    "wpm",
+    "raw_wpm",
+    "accuracy",
+    "wordlist",
+    "num_words",
+];
+
+impl ResultRecord {
+    /// CSV 中一行的各列，没有原始 WPM 时这一列为空
+    fn csv_fields(&self) -> [String; 6] {
+        [
+            self.timestamp.to_string(),
+            self.wpm.to_string(),
+            self.raw_wpm
+                .map_or_else(String::new, |raw_wpm| raw_wpm.to_string()),
+            self.accuracy.to_string(),
+            self.wordlist.clone(),
+            self.num_words.to_string(),
+        ]
+    }
+}
+
+/// 把 `records` 以 CSV 格式写入 `writer`，第一行是表头
+fn write_csv<W: Write>(records: &[ResultRecord], writer: W) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(CSV_HEADER)?;
+    for record in records {
+        writer.write_record(record.csv_fields())?;
+    }
+
+    writer.flush()
+}
+
+/// 把历史记录导出为 CSV 文件，以便在电子表格中分析
+///
+/// 没有历史记录时只写入表头
+pub fn export_csv(path: &Path) -> io::Result<()> {
+    write_csv(&load_history(), fs::File::create(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(wordlist: &str, raw_wpm: Option<f64>) -> ResultRecord {
+        ResultRecord {
+            timestamp: 1_700_000_000,
+            wpm: 61.5,
+            raw_wpm,
+            accuracy: 97.0,
+            wordlist: wordlist.to_string(),
+            num_words: 50,
+            missed_words: vec!["the".to_string()],
+        }
+    }
+
+    fn csv(records: &[ResultRecord]) -> String {
+        let mut out = Vec::new();
+        write_csv(records, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn empty_history_writes_only_the_header() {
+        assert_eq!(
+            csv(&[]),
+            "timestamp,wpm,raw_wpm,accuracy,wordlist,num_words\n"
+        );
+    }
+
+    #[test]
+    fn fields_are_quoted_and_missing_raw_wpm_is_empty() {
+        let records = [
+            record("top250", Some(64.25)),
+            record("my \"best\", words.txt", None),
+        ];
+        assert_eq!(
+            csv(&records),
+            "timestamp,wpm,raw_wpm,accuracy,wordlist,num_words\n\
+             1700000000,61.5,64.25,97,top250,50\n\
+             1700000000,61.5,,97,\"my \"\"best\"\", words.txt\",50\n"
+        );
+    }
+
+    #[test]
+    fn percentile_needs_enough_runs() {
+        let runs = |wpms: &[f64]| {
+            TypingHistory::new(
+                wpms.iter()
+                    .map(|&wpm| ResultRecord {
+                        wpm,
+                        ..record("top250", None)
+                    })
+                    .collect(),
+            )
+        };
+
+        assert_eq!(runs(&[40.0, 50.0, 60.0, 70.0]).percentile(65.0), None);
+        let history = runs(&[40.0, 50.0, 60.0, 70.0, 80.0]);
+        assert_eq!(history.percentile(65.0), Some(60.0));
+        // 与之前的速度相同时不算超过
+        assert_eq!(history.percentile(40.0), Some(0.0));
+        assert_eq!(history.percentile(90.0), Some(100.0));
+    }
+
+    #[test]
+    fn records_round_trip_and_old_records_still_load() {
+        let record = record("top250", None);
+        let line = serde_json::to_string(&record).unwrap();
+        // 没有原始 WPM 时不写这个字段，与较早的记录相同
+        assert!(!line.contains("raw_wpm"));
+        assert_eq!(serde_json::from_str::<ResultRecord>(&line).unwrap(), record);
+
+        let old = r#"{"timestamp":1,"wpm":40,"accuracy":90,"wordlist":"top250","num_words":25}"#;
+        let old: ResultRecord = serde_json::from_str(old).unwrap();
+        assert_eq!(old.raw_wpm, None);
+        assert!(old.missed_words.is_empty());
+    }
+
+    #[test]
+    fn appended_results_can_be_read_back() {
+        let path =
+            std::env::temp_dir().join(format!("typeing-{}-results.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let first = TypingStats::new("top250".to_string(), 10, 1);
+        let second = TypingStats::new("top1000".to_string(), 25, 2);
+        append_result_line(&path, &first.to_json()).unwrap();
+        append_result_line(&path, &second.to_json()).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let results: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["seed"].as_u64(), Some(1));
+        assert_eq!(results[1]["seed"].as_u64(), Some(2));
+        assert_eq!(results[1]["wordlist"].as_str(), Some("top1000"));
+
+        // 锁文件和临时文件都不会留下
+        assert!(!with_suffix(&path, ".lock").exists());
+        assert!(!with_suffix(&path, ".tmp").exists());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn appending_completes_an_unterminated_last_line() {
+        let path =
+            std::env::temp_dir().join(format!("typeing-{}-partial.jsonl", std::process::id()));
+        fs::write(&path, "{}").unwrap();
+
+        append_result_line(&path, "{\"wpm\":1}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}\n{\"wpm\":1}\n");
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use termion::event::Key;
+
+/// QWERTY 键盘上可以重新映射的按键，包括按住 shift 时的字符
+const QWERTY_KEYS: &str = "-=qwertyuiop[]asdfghjkl;'zxcvbnm,./_+QWERTYUIOP{}ASDFGHJKL:\"ZXCVBNM<>?";
+/// 与 [`QWERTY_KEYS`] 中同一个位置的按键在 Dvorak 布局中的字符
+const DVORAK_KEYS: &str = "[]',.pyfgcrl/=aoeuidhtns-;qjkxbmwvz{}\"<>PYFGCRL?+AOEUIDHTNS_:QJKXBMWVZ";
+/// 与 [`QWERTY_KEYS`] 中同一个位置的按键在 Colemak 布局中的字符
+const COLEMAK_KEYS: &str =
+    "-=qwfpgjluy;[]arstdhneio'zxcvbkm,./_+QWFPGJLUY:{}ARSTDHNEIO\"ZXCVBKM<>?";
+
+/// 练习的键盘布局
+///
+/// 操作系统使用 QWERTY 布局时，把按下的按键当作目标布局中同一个位置的字符
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// 不重新映射按键
+    Qwerty,
+    Dvorak,
+    Colemak,
+}
+
+impl Layout {
+    /// 从 QWERTY 布局到这个布局的映射
+    pub fn key_map(&self) -> KeyMap {
+        match self {
+            Self::Qwerty => KeyMap::default(),
+            Self::Dvorak => KeyMap::new(QWERTY_KEYS, DVORAK_KEYS),
+            Self::Colemak => KeyMap::new(QWERTY_KEYS, COLEMAK_KEYS),
+        }
+    }
+}
+
+/// 提前结束测试并显示已经输入的部分的结果的按键
+///
+/// 与 ctrl-c 不同，提前结束的测试仍然会显示和保存结果
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FinishKey {
+    Esc,
+    Tab,
+    Enter,
+    CtrlE,
+    /// 不能提前结束测试
+    None,
+}
+
+impl FinishKey {
+    /// 对应的按键，[`FinishKey::None`] 没有按键
+    pub fn key(&self) -> Option<Key> {
+        match self {
+            Self::Esc => Some(Key::Esc),
+            Self::Tab => Some(Key::Char('\t')),
+            Self::Enter => Some(Key::Char('\n')),
+            Self::CtrlE => Some(Key::Ctrl('e')),
+            Self::None => None,
+        }
+    }
+}
+
+/// 在检查输入是否正确之前重新映射输入的字符
+///
+/// 默认不改变任何字符
+#[derive(Clone, Debug, Default)]
+pub struct KeyMap {
+    map: HashMap<char, char>,
+}
+
+impl KeyMap {
+    /// 把 `from` 中的每个字符映射为 `to` 中同一个位置的字符
+    pub fn new(from: &str, to: &str) -> Self {
+        Self {
+            map: from.chars().zip(to.chars()).collect(),
+        }
+    }
+
+    /// 映射之后的字符，不在映射中的字符保持不变
+    pub fn remap(&self, c: char) -> char {
+        self.map.get(&c).copied().unwrap_or(c)
+    }
+}
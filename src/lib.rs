@@ -1,41 +1,123 @@
-use config::TypeingConfig;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use config::{Metric, OnError, ResultsLevel, TypeingConfig};
+use termion::color;
+use termion::event::{Event, Key, MouseButton, MouseEvent};
 
 pub mod config;
+pub mod history;
+pub mod keymap;
+pub mod stats;
 pub mod textgen;
+pub mod theme;
 pub mod tui;
 pub mod wordlists;
 
-use textgen::{RawWordSelector, WordSelector};
-use tui::{Text, TypeingTui};
+use keymap::KeyMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use stats::TypingStats;
+use textgen::{
+    read_to_string_lossy, CapitalizeSelector, CombinedSelector, FileTextSelector, NgramSelector,
+    NoRepeatSelector, NumberSelector, PunctuationSelector, QuoteSelector, RawWordSelector,
+    WordSelector,
+};
+use theme::Theme;
+use tui::{display_width, KeyReader, Text, TypeingTui};
+use wordlists::{os_wordlist_path, BuiltInWordlist, OS_WORDLIST_PATH};
+
+/// 结果页面中在应该输入空白字符的位置输错时显示的标记
+const WHITESPACE_ERROR_MARKER: char = '·';
+/// 自动跳过的单词中没有输入的字符被记录为这个字符
+const SKIPPED_CHAR: char = '\u{0}';
+
+/// 测试进行中刷新底部状态行的间隔
+const STATUS_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+/// 盲打模式的结果页面中最多显示的输错的单词数
+const MAX_MISTYPED_WORDS: usize = 5;
+/// 结果页面中显示输入的文本时每行的最大宽度
+const REVIEW_LINE_WIDTH: usize = 60;
+/// 结果页面中显示输入的文本时最多显示的行数
+const MAX_REVIEW_LINES: usize = 5;
+/// 一个单词后面最多可以输入的多余字符数
+const MAX_OVERFLOW_CHARS: usize = 10;
+/// 结果页面中显示的最慢的按键数
+const SLOWEST_KEYS_SHOWN: usize = 5;
+/// 详细的结果中显示的最慢的单词数
+const SLOWEST_WORDS_SHOWN: usize = 3;
+/// `--on-error flash` 时输错的字符反转颜色的时间
+const ERROR_FLASH_DURATION: Duration = Duration::from_millis(80);
+
+/// 测试完成时调用的函数，参见 [`Typeing::on_complete`]
+pub type CompletionCallback = Box<dyn FnMut(&TypingStats)>;
 
 /// 输入测试终端UI和逻辑
 pub struct Typeing {
-    tui: TypeingTui,
-    text: Vec<Text>,
+    /// 终端界面，用 [`Typeing::headless`] 创建时为 `None`
+    tui: Option<TypeingTui>,
+    keys: Option<KeyReader>,
     words: Vec<String>,
     word_selector: Box<dyn WordSelector>,
+    stats: TypingStats,
+    /// 最后一次完成的测试的统计数据
+    last_result: Option<TypingStats>,
+    /// `--repeat` 时这一轮中已经完成的测试
+    session_results: Vec<TypingStats>,
+    /// 不使用终端界面的测试的输入状态，参见 [`Typeing::feed_char`]
+    headless_state: Option<TestState>,
+    /// 不使用终端界面的计时测试结束的时间，在第一次按键时设置
+    headless_deadline: Option<Instant>,
+    /// 不使用终端界面的测试是否已经结束
+    headless_done: bool,
+    /// 选择单词使用的随机数种子
+    seed: u64,
+    /// 在检查是否正确之前重新映射输入的字符，参见 `--layout`
+    key_map: KeyMap,
+    /// 测试完成时调用的函数，参见 [`Typeing::on_complete`]
+    on_complete: Option<CompletionCallback>,
+    /// 状态行中显示的进度，参见 [`Typeing::progress_text`]
+    progress: String,
+    /// 最后一次测试是否因为没有修正的错误达到了 `--max-errors` 而提前结束
+    too_many_errors: bool,
+    /// 最后一次测试是否因为按下了 `--finish-key` 而提前结束
+    finished_early: bool,
+    /// 最后一次测试的速度超过了历史记录中百分之多少的测试，参见 [`history::TypingHistory::percentile`]
+    wpm_percentile: Option<f64>,
+    /// 在结果页面显示的警告，例如单词列表中有无效的 UTF-8 字节
+    warnings: Vec<String>,
+    /// 显示输入结果使用的颜色
+    theme: Theme,
     config: TypeingConfig,
 }
 
 /// 在Typeing中的错误
 pub struct TypeingError {
     pub msg: String,
+    /// 导致这个错误的底层错误，参见 [`std::error::Error::source`]
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
 }
 
 /// 转换 [`std::io::Error`] 到 [`TypeingError`]
 ///
-/// 只保留错误信息
+/// 保留错误信息，原来的错误作为 [`std::error::Error::source`]
 impl From<std::io::Error> for TypeingError {
     fn from(value: std::io::Error) -> Self {
         TypeingError {
             msg: value.to_string(),
+            source: Some(Box::new(value)),
         }
     }
 }
 
 impl From<String> for TypeingError {
     fn from(value: String) -> Self {
-        TypeingError { msg: value }
+        TypeingError {
+            msg: value,
+            source: None,
+        }
     }
 }
 
@@ -45,11 +127,2491 @@ impl std::fmt::Debug for TypeingError {
     }
 }
 
-impl<'a> Typeing {
-    pub fn new(config: TypeingConfig) -> Result<Self, TypeingError> {
+impl std::fmt::Display for TypeingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for TypeingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// 一次测试结束之后要做的事
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResultAction {
+    /// 退出程序
+    Quit,
+    /// 用一组新单词重新开始测试
+    NewWords,
+    /// 用同一组单词重新开始测试
+    Retry,
+}
+
+/// 一次测试中的输入状态
+///
+/// 不依赖终端界面，交互式测试和 [`Typeing::run_with_input`] 都使用它处理输入
+struct TestState {
+    /// 用户输入的字符
+    input: Vec<char>,
+    /// 当前显示的所有字符
+    original_text: Vec<char>,
+    /// 在单词后面输入的多余字符，键是单词后面空格的位置
+    overflows: HashMap<usize, Vec<char>>,
+    /// 当前页面中每个位置上输错的次数
+    failures: HashMap<usize, usize>,
+    /// 计时模式下，之前几页中输入的字符数
+    prev_pages_chars: usize,
+    /// 计时模式下，之前几页中输入正确的字符数
+    prev_pages_correct_chars: usize,
+    /// 计时模式下，之前几页中输入完的单词
+    prev_pages_words: Vec<TypedWord>,
+    /// 是否忽略大小写，参见 [`chars_match`]
+    ignore_case: bool,
+    /// `--highlight-word` 时突出显示的单词在当前页面中的开头
+    highlighted_word: usize,
+}
+
+/// 一个输入完的单词
+#[derive(Clone)]
+struct TypedWord {
+    /// 应该输入的字符，包括单词后面的空格
+    target: Vec<char>,
+    /// 实际输入的字符
+    typed: Vec<char>,
+    /// 在单词后面输入的多余字符
+    overflow: Vec<char>,
+    /// 是否忽略大小写，参见 [`chars_match`]
+    ignore_case: bool,
+}
+
+impl TypedWord {
+    fn is_correct(&self) -> bool {
+        self.target.len() == self.typed.len()
+            && self
+                .target
+                .iter()
+                .zip(&self.typed)
+                .all(|(&target, &typed)| chars_match(target, typed, self.ignore_case))
+            && self.overflow.is_empty()
+    }
+}
+
+/// 输入的字符 `typed` 是否与应该输入的字符 `target` 相同
+///
+/// `ignore_case` 为 `true` 时大小写不同的字母也算相同，参见 `--case-insensitive`
+fn chars_match(target: char, typed: char, ignore_case: bool) -> bool {
+    target == typed || (ignore_case && target.to_lowercase().eq(typed.to_lowercase()))
+}
+
+impl TestState {
+    /// `ignore_case` 为 `true` 时大小写不同的字母也算输入正确
+    fn new(original_text: Vec<char>, ignore_case: bool) -> Self {
+        Self {
+            input: Vec::new(),
+            original_text,
+            overflows: HashMap::new(),
+            failures: HashMap::new(),
+            prev_pages_chars: 0,
+            prev_pages_correct_chars: 0,
+            prev_pages_words: Vec::new(),
+            ignore_case,
+            highlighted_word: 0,
+        }
+    }
+
+    /// 当前页面中第 `start` 到第 `end` 个字符是否都输入正确
+    fn range_matches(&self, start: usize, end: usize) -> bool {
+        self.input[start..end]
+            .iter()
+            .zip(&self.original_text[start..end])
+            .all(|(&typed, &target)| chars_match(target, typed, self.ignore_case))
+    }
+
+    /// 到现在为止没有修正的错误数，包括输错的字符和多余字符
+    fn uncorrected_errors(&self) -> usize {
+        let overflows: usize = self.overflows.values().map(Vec::len).sum();
+        self.prev_pages_chars + self.input.len() - self.correct_chars() + overflows
+    }
+
+    /// 到现在为止输入正确的字符数
+    fn correct_chars(&self) -> usize {
+        self.prev_pages_correct_chars
+            + self
+                .input
+                .iter()
+                .zip(&self.original_text)
+                .filter(|(&typed, &original)| chars_match(original, typed, self.ignore_case))
+                .count()
+    }
+
+    /// 是否已经输入了当前页面的所有字符
+    fn page_complete(&self) -> bool {
+        self.input.len() >= self.original_text.len()
+    }
+
+    /// 退格键可以删除到的最早位置
+    ///
+    /// `free_backspace` 为 `false` 时，不能删除最后一个正确输入的单词（包括后面的空格）和它之前的字符，
+    /// 否则可以删除到当前页面的开头
+    fn min_input_len(&self, free_backspace: bool) -> usize {
+        if free_backspace {
+            return 0;
+        }
+
+        // 从后向前检查每一个已经输入完的单词
+        let mut end = self.input.len();
+        while end > 0 {
+            if self.original_text[end - 1] == ' ' {
+                let start = self.original_text[..end - 1]
+                    .iter()
+                    .rposition(|c| *c == ' ')
+                    .map_or(0, |space| space + 1);
+                let overflowed = self
+                    .overflows
+                    .get(&(end - 1))
+                    .is_some_and(|overflow| !overflow.is_empty());
+                if !overflowed && self.range_matches(start, end) {
+                    return end;
+                }
+            }
+            end -= 1;
+        }
+
+        0
+    }
+
+    /// 按下 ctrl-w 之后输入的长度，即当前单词的开头
+    ///
+    /// 光标正好在一个单词的开头时，删除前一个单词和它后面的空格
+    fn word_start(&self, min_len: usize) -> usize {
+        let mut len = self.input.len();
+        while len > min_len && self.original_text[len - 1] == ' ' {
+            len -= 1;
+        }
+        while len > min_len && self.original_text[len - 1] != ' ' {
+            len -= 1;
+        }
+
+        len
+    }
+
+    /// 光标是否在一个完全正确地输入的单词后面，即下一个字符是空格，
+    /// 当前单词的每个字符都输入正确并且没有多余字符
+    fn word_done_correctly(&self) -> bool {
+        let len = self.input.len();
+        if self.original_text.get(len) != Some(&' ') || !self.current_overflow().is_empty() {
+            return false;
+        }
+        let start = self.original_text[..len]
+            .iter()
+            .rposition(|c| *c == ' ')
+            .map_or(0, |space| space + 1);
+
+        self.range_matches(start, len)
+    }
+
+    /// 光标位置的多余字符
+    fn current_overflow(&self) -> &[char] {
+        self.overflows
+            .get(&self.input.len())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// 光标位置的多余字符改变之后需要显示的内容
+    fn overflow_effect(&self) -> KeyEffect {
+        KeyEffect::Overflow {
+            overflow: self.current_overflow().to_vec(),
+            rest: self.original_text[self.input.len()..].iter().collect(),
+        }
+    }
+
+    /// 删除输入的字符，直到只剩下 `len` 个字符
+    ///
+    /// 会先删除经过的位置上的多余字符，但是保留 `len` 位置上的多余字符
+    fn delete_to(&mut self, len: usize) -> Vec<KeyEffect> {
+        let mut effects = Vec::new();
+
+        while self.input.len() > len {
+            if !self.current_overflow().is_empty() {
+                self.overflows.remove(&self.input.len());
+                effects.push(self.overflow_effect());
+            }
+
+            self.input.pop();
+            effects.push(KeyEffect::Deleted {
+                target: self.original_text[self.input.len()],
+            });
+        }
+
+        effects
+    }
+
+    /// 删除光标位置的多余字符，然后删除输入的字符直到只剩下 `len` 个字符，参见 [`TestState::delete_to`]
+    fn rewind_to(&mut self, len: usize) -> Vec<KeyEffect> {
+        let mut effects = Vec::new();
+        if !self.current_overflow().is_empty() {
+            self.overflows.remove(&self.input.len());
+            effects.push(self.overflow_effect());
+        }
+        effects.extend(self.delete_to(len));
+
+        effects
+    }
+
+    /// 包含第 `index` 个字符的单词的开头
+    fn word_start_at(&self, index: usize) -> usize {
+        self.original_text[..index]
+            .iter()
+            .rposition(|c| *c == ' ')
+            .map_or(0, |space| space + 1)
+    }
+
+    /// 刚刚输入完的单词的目标文本，即刚刚输入了单词后面的空格或者页面的最后一个字符。
+    /// 光标在单词中间时为 `None`
+    fn just_completed_word(&self) -> Option<String> {
+        let len = self.input.len();
+        let end = if len > 0 && self.original_text[len - 1] == ' ' {
+            len - 1
+        } else if len > 0 && self.page_complete() {
+            len
+        } else {
+            return None;
+        };
+
+        Some(
+            self.original_text[self.word_start_at(end)..end]
+                .iter()
+                .collect(),
+        )
+    }
+
+    /// 光标所在的单词中还没有输入的字符，光标在单词后面的空格上时为空
+    fn current_word_rest(&self) -> String {
+        let len = self.input.len();
+        self.original_text[len..]
+            .iter()
+            .take_while(|c| **c != ' ')
+            .collect()
+    }
+
+    /// 计时模式下换到新的一页，之前输入的字符仍然计入结果
+    fn turn_page(&mut self, original_text: Vec<char>) {
+        let words = self.page_words();
+        self.prev_pages_words.extend(words);
+        self.prev_pages_correct_chars = self.correct_chars();
+        self.prev_pages_chars += self.input.len();
+        self.input.clear();
+        self.overflows.clear();
+        self.failures.clear();
+        self.original_text = original_text;
+        self.highlighted_word = 0;
+    }
+
+    /// 跳过当前单词剩下的字符和后面的空格，这些字符都算作输错
+    fn skip_word(&mut self) -> Vec<KeyEffect> {
+        let mut effects = Vec::new();
+
+        while !self.page_complete() {
+            let target = self.original_text[self.input.len()];
+            let typed = if target == ' ' { ' ' } else { SKIPPED_CHAR };
+            self.input.push(typed);
+            effects.push(KeyEffect::Typed { target, typed });
+            if target == ' ' {
+                break;
+            }
+        }
+
+        effects
+    }
+
+    /// 测试结束时计入结果的字符数
+    ///
+    /// 没有输入完所有字符（例如计时模式下时间用完）时只计算已经完成的单词
+    fn counted_chars(&self) -> usize {
+        self.prev_pages_chars + self.completed_len()
+    }
+
+    /// 当前页面中已经输入完的单词的字符数
+    fn completed_len(&self) -> usize {
+        if self.page_complete() {
+            return self.input.len();
+        }
+
+        self.original_text[..self.input.len()]
+            .iter()
+            .rposition(|c| *c == ' ')
+            .map_or(0, |last_space| last_space + 1)
+    }
+
+    /// 当前页面中已经输入完的单词
+    fn page_words(&self) -> Vec<TypedWord> {
+        let len = self.completed_len();
+        let mut words = Vec::new();
+        let mut start = 0;
+
+        for (i, c) in self.original_text[..len].iter().enumerate() {
+            if *c == ' ' || i + 1 == len {
+                words.push(TypedWord {
+                    target: self.original_text[start..=i].to_vec(),
+                    typed: self.input[start..=i].to_vec(),
+                    overflow: self.overflows.get(&i).cloned().unwrap_or_default(),
+                    ignore_case: self.ignore_case,
+                });
+                start = i + 1;
+            }
+        }
+
+        words
+    }
+
+    /// 整个测试中输入完的单词数，与 [`TestState::typed_words`] 的长度相同
+    fn completed_words(&self) -> usize {
+        let len = self.completed_len();
+        let page_words = self.original_text[..len]
+            .iter()
+            .filter(|c| **c == ' ')
+            .count()
+            + usize::from(len > 0 && self.original_text[len - 1] != ' ');
+
+        self.prev_pages_words.len() + page_words
+    }
+
+    /// 整个测试中输入完的单词
+    fn typed_words(&self) -> Vec<TypedWord> {
+        let mut words = self.prev_pages_words.clone();
+        words.extend(self.page_words());
+        words
+    }
+
+    /// 整个测试中输入完但是没有修正的输错的单词，与 [`TestState::error_words`] 一一对应
+    ///
+    /// 单词来自每个单词应该输入的字符，所以计时模式下也包括之前的页面中的单词
+    fn missed_words(&self) -> Vec<String> {
+        self.typed_words()
+            .iter()
+            .filter(|word| !word.is_correct())
+            .map(|word| {
+                word.target
+                    .iter()
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// 整个测试中输入完但是没有修正的输错的单词的位置
+    fn error_words(&self) -> Vec<usize> {
+        self.typed_words()
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| !word.is_correct())
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// 处理一次按键之后测试的状态
+enum TestStatus {
+    /// 还有需要输入的按键
+    NotDone,
+    /// 输入了最后一个字符
+    Done,
+    /// 计时模式下时间已经用完
+    TimeUp,
+    /// 没有修正的错误达到了 `--max-errors`
+    TooManyErrors,
+    /// 用户按下了 `--finish-key`，只统计已经输入的部分
+    FinishedEarly,
+    /// 用户想要退出测试
+    Quit,
+    /// 用户想要重新开始测试
+    Restart(ResultAction),
+    /// 计时模式下输入完了当前页面，需要显示新的单词
+    PageDone,
+}
+
+/// 处理一次按键之后需要在屏幕上显示的改变
+enum KeyEffect {
+    /// 这次按键输入错误，紧接着的 `Typed` 或者 `Overflow` 显示的是输错的字符
+    Mistake,
+    /// 输入了一个字符，`target` 是这个位置上应该输入的字符
+    Typed { target: char, typed: char },
+    /// 删除了一个字符，`target` 是这个位置上原本的字符
+    Deleted { target: char },
+    /// 光标位置的多余字符变成了 `overflow`，`rest` 是从光标位置开始还没有输入的文本
+    Overflow { overflow: Vec<char>, rest: String },
+}
+
+/// 用户输入一个字符之后显示的文本
+///
+/// 输入正确时为主题中正确颜色的输入的字符（忽略大小写时可能与原字符的大小写不同），否则为错误颜色的原字符。
+/// 有颜色的空格是看不见的，所以输错的空白字符显示为反转颜色的空格，
+/// 在应该输入字母的位置输入了空白字符时原字符还带有下划线。
+/// `blind` 为 `true` 时不显示是否正确，只显示普通颜色的原字符
+fn typed_text(target: char, typed: char, ignore_case: bool, blind: bool, theme: &Theme) -> Text {
+    if blind {
+        Text::from(target)
+    } else if chars_match(target, typed, ignore_case) {
+        theme.correct(Text::from(typed))
+    } else if target.is_whitespace() {
+        theme.incorrect(Text::from(' ')).with_reverse()
+    } else if typed.is_whitespace() {
+        theme.incorrect(Text::from(target)).with_underline()
+    } else {
+        theme.incorrect(Text::from(target))
+    }
+}
+
+/// 在单词后面输入的多余字符
+///
+/// 总是显示为错误颜色，`blind` 为 `true` 时显示为普通颜色
+fn overflow_text(overflow: &[char], blind: bool, theme: &Theme) -> Text {
+    let text = Text::from(overflow.iter().collect::<String>());
+    if blind {
+        text
+    } else {
+        theme.incorrect(text)
+    }
+}
+
+/// 结果页面中实际输入的一个字符，输入正确时为正确颜色，否则为错误颜色
+fn mistyped_char_text(target: char, typed: char, ignore_case: bool, theme: &Theme) -> Text {
+    if chars_match(target, typed, ignore_case) {
+        theme.correct(Text::from(typed))
+    } else if typed.is_whitespace() || typed == SKIPPED_CHAR {
+        theme.incorrect(Text::from(WHITESPACE_ERROR_MARKER))
+    } else {
+        theme.incorrect(Text::from(typed))
+    }
+}
+
+/// 在结果页面中列出输错的单词
+///
+/// 每一行是应该输入的单词和实际输入的字符，输错的字符为错误颜色
+fn mistyped_lines(words: &[TypedWord], theme: &Theme) -> Vec<Vec<Text>> {
+    let mistyped: Vec<&TypedWord> = words.iter().filter(|word| !word.is_correct()).collect();
+    if mistyped.is_empty() {
+        return vec![vec![theme.correct(Text::from("没有输错的单词"))]];
+    }
+
+    let mut lines = vec![vec![Text::from("输错的单词:")]];
+    for word in mistyped.iter().take(MAX_MISTYPED_WORDS) {
+        let target: String = word.target.iter().collect();
+        let mut line = vec![
+            Text::from(target.trim_end().to_string()),
+            Text::from(" -> ").with_faint(),
+        ];
+        // 多余字符显示在单词和后面的空格之间
+        let word_len = word.target.len() - usize::from(word.target.last() == Some(&' '));
+        for i in 0..word.target.len() {
+            if i == word_len && !word.overflow.is_empty() {
+                line.push(overflow_text(&word.overflow, false, theme));
+            }
+            line.push(mistyped_char_text(
+                word.target[i],
+                word.typed[i],
+                word.ignore_case,
+                theme,
+            ));
+        }
+        lines.push(line);
+    }
+    if mistyped.len() > MAX_MISTYPED_WORDS {
+        lines.push(vec![Text::from(format!(
+            "还有 {} 个输错的单词",
+            mistyped.len() - MAX_MISTYPED_WORDS
+        ))
+        .with_faint()]);
+    }
+
+    lines
+}
+
+/// 在结果页面中显示输入的文本，含有没有修正的错误的单词加上下划线
+///
+/// `error_words` 是这些单词的位置，参见 [`TypingStats::error_words`]。
+/// 最多显示 [`MAX_REVIEW_LINES`] 行
+fn review_lines(words: &[TypedWord], error_words: &[usize], theme: &Theme) -> Vec<Vec<Text>> {
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    let mut width = 0;
+
+    for (i, word) in words.iter().enumerate() {
+        let target: String = word.target.iter().collect();
+        let target = target.trim_end();
+        let word_width = display_width(target) + 1;
+        if width > 0 && width + word_width > REVIEW_LINE_WIDTH {
+            lines.push(std::mem::take(&mut line));
+            width = 0;
+            if lines.len() == MAX_REVIEW_LINES {
+                lines.push(vec![Text::from("...").with_faint()]);
+                return lines;
+            }
+        }
+
+        let text = Text::from(target.to_string());
+        if error_words.binary_search(&i).is_ok() {
+            line.push(theme.incorrect(text).with_underline());
+        } else {
+            line.push(text.with_faint());
+        }
+        line.push(Text::from(" "));
+        width += word_width;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// 文件 `path` 中有无效的 UTF-8 字节时显示的警告
+fn invalid_utf8_warning(path: &str) -> String {
+    format!("{} 中有无效的 UTF-8 字节，一些字符被替换了", path)
+}
+
+/// 把 `--append-wordlist` 文件中的单词添加到 `selector` 的单词池中
+///
+/// 没有指定文件时原样返回 `selector`
+fn append_words(
+    selector: RawWordSelector,
+    append_path: Option<&str>,
+    warnings: &mut Vec<String>,
+) -> Result<RawWordSelector, TypeingError> {
+    let Some(path) = append_path else {
+        return Ok(selector);
+    };
+    let (contents, replaced) = read_to_string_lossy(Path::new(path))
+        .map_err(|err| TypeingError::from(format!("无法读取要添加的单词列表 {}: {}", path, err)))?;
+    if replaced {
+        warnings.push(invalid_utf8_warning(path));
+    }
+
+    selector
+        .with_extra_words(&contents)
+        .map_err(|err| TypeingError::from(format!("无法添加单词列表 {}: {}", path, err)))
+}
+
+/// 使用内置单词列表的 [`WordSelector`]
+///
+/// `weighted` 为 `true` 时按照单词的排名加权选择，参见 [`RawWordSelector::with_rank_weighting`]。
+/// `append_path` 文件中的单词会添加到这个单词列表中。读取文件时的警告添加到 `warnings` 中
+fn builtin_word_selector(
+    wordlist: BuiltInWordlist,
+    seed: u64,
+    weighted: bool,
+    append_path: Option<&str>,
+    warnings: &mut Vec<String>,
+) -> Result<Box<dyn WordSelector>, TypeingError> {
+    if let Some(word_list) = wordlist.contents() {
+        Ok(Box::new(
+            append_words(RawWordSelector::from_str(word_list)?, append_path, warnings)?
+                .with_seed(seed)
+                .with_rank_weighting(weighted),
+        ))
+    } else if let BuiltInWordlist::OS = wordlist {
+        let path = os_wordlist_path().ok_or_else(|| {
+            TypeingError::from(format!(
+                "找不到操作系统的单词列表 {}，请用 --wordlist 选择一个内置的单词列表，例如 top1000",
+                OS_WORDLIST_PATH
+            ))
+        })?;
+        let selector = RawWordSelector::from_path(path.clone()).map_err(|err| {
+            TypeingError::from(format!(
+                "无法读取操作系统的单词列表 {}: {}，请用 --wordlist 选择一个内置的单词列表",
+                path.display(),
+                err
+            ))
+        })?;
+        if selector.replaced_invalid_utf8() {
+            warnings.push(invalid_utf8_warning(&path.display().to_string()));
+        }
+        Ok(Box::new(
+            append_words(selector, append_path, warnings)?
+                .with_seed(seed)
+                .with_rank_weighting(weighted),
+        ))
+    } else if !cfg!(feature = "builtin-wordlists") {
+        Err(builtin_wordlists_disabled())
+    } else {
+        Err(TypeingError::from("未定义的单词列表或路径".to_owned()))
+    }
+}
+
+/// 编译时没有启用 `builtin-wordlists` 特性，却选择了内置的单词列表或者名言时的错误
+fn builtin_wordlists_disabled() -> TypeingError {
+    TypeingError::from(
+        "编译时没有启用 builtin-wordlists 特性，没有内置的单词列表和名言，\
+         请用 --file、--stdin 或 --text-file 提供单词，或者用 --wordlist os 使用操作系统的单词列表"
+            .to_string(),
+    )
+}
+
+impl Typeing {
+    /// 根据配置创建单词选择器，还没有终端界面和单词
+    fn build(config: TypeingConfig) -> Result<Self, TypeingError> {
+        config.validate()?;
+
+        // 没有指定种子时随机选择一个，并在结果中显示，以便重现这次测试
+        let seed = config.seed.unwrap_or_else(rand::random);
+        // 每个选择器使用从这个种子派生出的种子，相同的种子总是得到相同的单词
+        let mut seeds = StdRng::seed_from_u64(seed);
+        let mut warnings = Vec::new();
+
+        let word_selector: Box<dyn WordSelector> = if config.quote {
+            Box::new(
+                QuoteSelector::new(
+                    wordlists::quotes().ok_or_else(builtin_wordlists_disabled)?,
+                    config.quote_length,
+                )?
+                .with_seed(seeds.gen()),
+            )
+        } else if let Some(text_path) = config.text_file.clone() {
+            let selector = FileTextSelector::from_path(PathBuf::from(&text_path))?;
+            if selector.replaced_invalid_utf8() {
+                warnings.push(invalid_utf8_warning(&text_path));
+            }
+            Box::new(selector)
+        } else if config.stdin {
+            // 终端界面还没有创建，这时读取标准输入不会与按键冲突。按键由 KeyReader 从终端读取
+            let contents = std::io::read_to_string(std::io::stdin())?;
+            if contents.trim().is_empty() {
+                return Err(TypeingError::from("标准输入中没有任何单词".to_string()));
+            }
+
+            Box::new(
+                RawWordSelector::from_str(&contents)?
+                    .with_seed(seeds.gen())
+                    .with_rank_weighting(config.weighted),
+            )
+        } else if config.drill_errors {
+            let missed_words = history::missed_words();
+            if missed_words.is_empty() {
+                return Err(TypeingError::from("历史记录中还没有输错的单词".to_string()));
+            }
+
+            // 按原样练习输错过的单词，不像单词列表那样过滤长度和转换大小写
+            Box::new(RawWordSelector::with_rng(
+                missed_words,
+                StdRng::seed_from_u64(seeds.gen()),
+            )?)
+        } else if let Some(length) = config.ngram {
+            Box::new(NgramSelector::new(length.into())?.with_seed(seeds.gen()))
+        } else if let Some(wordlist_path) = config.wordlist_file.clone() {
+            let selector = RawWordSelector::from_path(PathBuf::from(&wordlist_path))?;
+            if selector.replaced_invalid_utf8() {
+                warnings.push(invalid_utf8_warning(&wordlist_path));
+            }
+            Box::new(
+                selector
+                    .with_seed(seeds.gen())
+                    .with_rank_weighting(config.weighted),
+            )
+        } else if let Some(word_list) = config.language.contents() {
+            Box::new(
+                append_words(
+                    RawWordSelector::from_str(word_list)?,
+                    config.append_wordlist.as_deref(),
+                    &mut warnings,
+                )?
+                .with_seed(seeds.gen())
+                .with_rank_weighting(config.weighted),
+            )
+        } else {
+            let mut selectors = config
+                .wordlist
+                .iter()
+                .map(|&wordlist| {
+                    builtin_word_selector(
+                        wordlist,
+                        seeds.gen(),
+                        config.weighted,
+                        config.append_wordlist.as_deref(),
+                        &mut warnings,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if selectors.len() == 1 {
+                selectors.remove(0)
+            } else {
+                let weights = if config.wordlist_weights.is_empty() {
+                    vec![1; selectors.len()]
+                } else {
+                    config.wordlist_weights.clone()
+                };
+                Box::new(CombinedSelector::new(selectors, weights)?.with_seed(seeds.gen()))
+            }
+        };
+
+        // 名言和文本文件按原来的顺序显示单词，不需要避免重复
         let word_selector: Box<dyn WordSelector> =
-            if let Some(wordlist_path) = config.wordlist_file.clone() {
+            if config.allow_repeats || config.quote || config.text_file.is_some() {
+                word_selector
+            } else {
+                Box::new(NoRepeatSelector::new(word_selector))
+            };
+
+        let word_selector: Box<dyn WordSelector> = if config.numbers {
+            Box::new(NumberSelector::new(word_selector).with_seed(seeds.gen()))
+        } else {
+            word_selector
+        };
+
+        let word_selector: Box<dyn WordSelector> = if let Some(ratio) = config.capitals {
+            Box::new(CapitalizeSelector::new(word_selector, ratio).with_seed(seeds.gen()))
+        } else {
+            word_selector
+        };
+
+        let word_selector: Box<dyn WordSelector> = if config.punctuation {
+            Box::new(PunctuationSelector::new(word_selector).with_seed(seeds.gen()))
+        } else {
+            word_selector
+        };
+
+        Ok(Typeing {
+            tui: None,
+            keys: None,
+            words: Vec::new(),
+            word_selector,
+            stats: TypingStats::default(),
+            last_result: None,
+            session_results: Vec::new(),
+            headless_state: None,
+            headless_deadline: None,
+            headless_done: false,
+            seed,
+            progress: String::new(),
+            wpm_percentile: None,
+            too_many_errors: false,
+            finished_early: false,
+            on_complete: None,
+            key_map: config.layout.key_map(),
+            warnings,
+            theme: config.color_theme(),
+            config,
+        })
+    }
+
+    /// 创建一个在终端中进行的交互式测试
+    pub fn new(config: TypeingConfig) -> Result<Self, TypeingError> {
+        let mut typeing = Self::build(config)?;
+        typeing.tui = Some(
+            TypeingTui::new()?
+                .with_line_layout(
+                    typeing.config.line_width_pct,
+                    typeing.config.max_words_per_line,
+                )
+                .with_words_per_line(typeing.config.words_per_line)
+                .with_break_long_words(typeing.config.break_long_words)
+                .with_cursor_style(typeing.config.cursor)
+                .with_smooth_caret(typeing.config.smooth_caret)
+                .with_final_space(typeing.config.require_final_space)
+                .with_mouse(typeing.config.mouse)
+                .with_minimal(typeing.config.minimal)
+                .with_block_caret(typeing.config.block_caret)
+                .with_theme(typeing.theme),
+        );
+        typeing.keys = Some(KeyReader::new());
+
+        typeing.restart(false)?;
+
+        Ok(typeing)
+    }
+
+    /// 创建一个不使用终端的测试，用 [`Typeing::run_with_input`] 输入按键
+    ///
+    /// 这样可以把 Typeing 嵌入到其他程序中，或者在没有终端的环境中使用
+    pub fn headless(config: TypeingConfig) -> Result<Self, TypeingError> {
+        let mut typeing = Self::build(config)?;
+
+        typeing.restart(false)?;
+
+        Ok(typeing)
+    }
+
+    /// 使用 `word_selector` 选择单词，例如 [`textgen::IterSelector`]，并用它选出的单词重新开始测试
+    ///
+    /// 代替配置中的单词列表，`--numbers` 等修改单词的选项不会作用于它选出的单词
+    pub fn with_word_selector(
+        mut self,
+        word_selector: Box<dyn WordSelector>,
+    ) -> Result<Self, TypeingError> {
+        self.word_selector = word_selector;
+        self.restart(false)?;
+
+        Ok(self)
+    }
+
+    /// 当前测试中的单词
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// 重新开始测试
+    ///
+    /// `keep_words` 为 `true` 时重新输入同一组单词，否则用一组新单词
+    pub fn restart(&mut self, keep_words: bool) -> Result<(), TypeingError> {
+        if !keep_words {
+            self.words = self.word_selector.new_words(self.config.num_words)?;
+        }
+        if self.words.is_empty() {
+            return Err(TypeingError::from("没有可以输入的单词".to_string()));
+        }
+        if let Some(tui) = &mut self.tui {
+            tui.scroll_to_top();
+        }
+        // 名言和文本文件的结尾可能与设置的单词数不同
+        self.stats = TypingStats::new(self.config.text_name(), self.words.len(), self.seed);
+        self.headless_state = None;
+        self.headless_deadline = None;
+        self.headless_done = false;
+        self.progress = self.progress_text(0, None);
+
+        self.show_page()
+    }
+
+    /// 清空屏幕并显示一组新单词
+    ///
+    /// 单词选择器没有更多单词时返回 `false`，当前的单词和屏幕都不会改变
+    fn next_page(&mut self) -> Result<bool, TypeingError> {
+        let words = self.word_selector.new_words(self.config.num_words)?;
+        if words.is_empty() {
+            return Ok(false);
+        }
+
+        self.words = words;
+        if let Some(tui) = &mut self.tui {
+            tui.scroll_to_top();
+        }
+        self.show_page()?;
+        Ok(true)
+    }
+
+    /// 计时模式下输入完当前页面之后换到一组新单词
+    ///
+    /// 单词选择器没有更多单词时测试结束，返回 [`TestStatus::Done`]
+    fn turn_page(&mut self, state: &mut TestState) -> Result<TestStatus, TypeingError> {
+        if !self.next_page()? {
+            return Ok(TestStatus::Done);
+        }
+
+        state.turn_page(self.original_text());
+        self.highlight_word(state)?;
+        Ok(TestStatus::NotDone)
+    }
+
+    /// 计时模式下在当前的单词后面添加一组新单词，并重新显示已经输入的进度
+    ///
+    /// 已经输入的字符和光标的位置都不会改变，单词超出终端的高度时会滚动显示。
+    /// 单词选择器没有更多单词时不做任何事，输入完最后一个字符时测试结束。
+    /// 没有终端界面的测试只添加单词
+    fn refill(&mut self, state: &mut TestState) -> Result<(), TypeingError> {
+        let words = self.word_selector.new_words(self.config.num_words)?;
+        if words.is_empty() {
+            return Ok(());
+        }
+
+        self.words.extend(words);
+        // 原来的文本是新文本的前缀，所以已经输入的字符仍然对应相同的位置
+        state.original_text = self.original_text();
+        if self.tui.is_none() {
+            return Ok(());
+        }
+        self.relayout(state)
+    }
+
+    /// 清空屏幕并显示当前的单词
+    ///
+    /// 没有终端界面时不做任何事
+    fn show_page(&mut self) -> Result<(), TypeingError> {
+        let Some(tui) = &mut self.tui else {
+            return Ok(());
+        };
+        tui.reset_screen()?;
+
+        let live_wpm = self.stats.live_wpm(0);
+        self.display_status(live_wpm)?;
+
+        self.show_words()?;
+
+        Ok(())
+    }
+
+    /// 状态行中显示的进度
+    ///
+    /// 计时模式下是剩余的秒数，还没有开始计时（`deadline` 为 `None`）时是全部的时间。
+    /// 否则是输入完的单词数 `words_done` 和总单词数
+    fn progress_text(&self, words_done: usize, deadline: Option<Instant>) -> String {
+        match self.config.time {
+            Some(seconds) => {
+                let remaining = deadline.map_or(Duration::from_secs(seconds), |deadline| {
+                    deadline.saturating_duration_since(Instant::now())
+                });
+                format!("剩余 {} 秒", remaining.as_secs_f64().ceil() as u64)
+            }
+            None => format!("单词 {}/{}", words_done, self.words.len()),
+        }
+    }
+
+    /// 在屏幕底部显示实时速度、进度和快捷键
+    ///
+    /// 不会把光标移回当前字符
+    fn display_status(&mut self, live_wpm: f64) -> Result<(), TypeingError> {
+        let progress = self.progress.clone();
+        self.tui()?.display_lines_bottom::<&[Text], _>(&[
+            &[
+                Text::from(format!("{:.0} wpm", live_wpm)).with_color(color::Green),
+                Text::from(format!("  {}", progress)).with_faint(),
+            ],
+            &[
+                Text::from("ctrl-r").with_color(color::Blue),
+                Text::from(" 重新开始, ").with_faint(),
+                Text::from("ctrl-t").with_color(color::Blue),
+                Text::from(" 重试, ").with_faint(),
+                Text::from("ctrl-p").with_color(color::Blue),
+                Text::from(" 暂停, ").with_faint(),
+                Text::from("ctrl-c").with_color(color::Blue),
+                Text::from(" 退出 ").with_faint(),
+            ],
+        ])?;
+
+        Ok(())
+    }
+
+    fn show_words(&mut self) -> Result<(), TypeingError> {
+        if let Some(tui) = &mut self.tui {
+            tui.display_words(&self.words)?;
+        }
+        Ok(())
+    }
+
+    /// 终端界面
+    ///
+    /// 用 [`Typeing::headless`] 创建的测试没有终端界面，返回错误
+    fn tui(&mut self) -> Result<&mut TypeingTui, TypeingError> {
+        self.tui
+            .as_mut()
+            .ok_or_else(|| TypeingError::from("这个测试没有终端界面".to_string()))
+    }
 
+    /// 读取按键的线程
+    fn keys(&mut self) -> Result<&mut KeyReader, TypeingError> {
+        self.keys
+            .as_mut()
+            .ok_or_else(|| TypeingError::from("这个测试没有终端界面".to_string()))
+    }
+
+    /// 需要输入的所有字符
+    ///
+    /// 显示时单词之间和每行的末尾都有一个空格，所以与单词用空格连接起来相同。
+    /// 使用 `--require-final-space` 时最后一个单词后面也有一个空格
+    fn original_text(&self) -> Vec<char> {
+        let mut text = self.words.join(" ");
+        if self.config.require_final_space {
+            text.push(' ');
+        }
+
+        text.chars().collect()
+    }
+
+    /// 不使用终端界面进行一次测试，返回测试的统计数据
+    ///
+    /// `input` 中的每个字符相当于一次按键，`'\u{8}'` 和 `'\u{7f}'` 相当于退格键，
+    /// `'\u{17}'` 相当于 ctrl-w。
+    /// 输入完所有单词或者 `input` 结束时测试结束，没有输入完的单词不计入结果。
+    /// 计时模式下时间用完、或者没有修正的错误达到 `--max-errors` 时测试也会结束。
+    /// 计时模式下输入完所有单词时与终端界面一样在后面添加一组新单词，单词之间仍然需要输入空格。
+    ///
+    /// 这个方法不会写入 `stdout`，也不会把结果保存到历史记录
+    pub fn run_with_input(
+        &mut self,
+        input: impl Iterator<Item = char>,
+    ) -> Result<TypingStats, TypeingError> {
+        self.headless_state = None;
+        self.headless_deadline = None;
+        self.headless_done = false;
+
+        for c in input {
+            if self.feed_char(c)? {
+                break;
             }
+        }
+
+        Ok(self.finish_input())
+    }
+
+    /// 在不使用终端界面的测试中输入一个字符，返回测试是否已经结束
+    ///
+    /// 字符的含义与 [`Typeing::run_with_input`] 相同。测试结束之后输入的字符会被忽略。
+    /// 与 [`Typeing::completed_words`] 和 [`Typeing::current_char_index`] 一起，
+    /// 可以由外部的程序一个字符一个字符地推进测试，并随时查看进度。
+    ///
+    /// 例如比赛模式：使用同一个种子的两个测试有相同的单词，
+    /// 把每个玩家的按键交给各自的测试，用进度显示谁领先，第一个结束的玩家获胜
+    ///
+    /// ```no_run
+    /// use typeing::config::TypeingConfig;
+    /// use typeing::Typeing;
+    ///
+    /// let config = || TypeingConfig::builder().seed(42).num_words(10).build();
+    /// let mut players = [Typeing::headless(config()?)?, Typeing::headless(config()?)?];
+    /// assert_eq!(players[0].words(), players[1].words());
+    ///
+    /// // 实际的程序中按键来自每个玩家的输入
+    /// let keys = [players[0].words().join(" "), players[1].words().join(" ").to_uppercase()];
+    /// let mut keys = [keys[0].chars(), keys[1].chars()];
+    /// let winner = 'race: loop {
+    ///     for (i, player) in players.iter_mut().enumerate() {
+    ///         let done = match keys[i].next() {
+    ///             Some(c) => player.feed_char(c)?,
+    ///             None => true,
+    ///         };
+    ///         if done {
+    ///             break 'race i;
+    ///         }
+    ///     }
+    ///     println!(
+    ///         "{} : {} 个单词",
+    ///         players[0].completed_words(),
+    ///         players[1].completed_words()
+    ///     );
+    /// };
+    /// let stats = players[winner].finish_input();
+    /// println!("玩家 {} 获胜，{:.1} wpm", winner + 1, stats.wpm());
+    /// # Ok::<(), typeing::TypeingError>(())
+    /// ```
+    pub fn feed_char(&mut self, c: char) -> Result<bool, TypeingError> {
+        if self.headless_done {
+            return Ok(true);
+        }
+        if self
+            .headless_deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.finish_input();
+            return Ok(true);
+        }
+
+        let mut state = match self.headless_state.take() {
+            Some(state) => state,
+            None => TestState::new(self.original_text(), self.config.case_insensitive),
+        };
+        let key = match c {
+            '\u{8}' | '\u{7f}' => Key::Backspace,
+            '\u{17}' => Key::Ctrl('w'),
+            '\u{1b}' => Key::Esc,
+            c => Key::Char(c),
+        };
+        let (status, _) = self.handle_key(&mut state, key);
+        // 计时模式下与终端界面一样在后面添加新的单词，单词用完时测试结束
+        let result = match status {
+            TestStatus::PageDone => self.refill(&mut state),
+            _ => Ok(()),
+        };
+        let page_complete = state.page_complete();
+        self.headless_state = Some(state);
+        result?;
+
+        if self.headless_deadline.is_none() {
+            self.headless_deadline = self
+                .config
+                .time
+                .map(|seconds| Instant::now() + Duration::from_secs(seconds));
+        }
+        if page_complete
+            || matches!(
+                status,
+                TestStatus::Done | TestStatus::FinishedEarly | TestStatus::TooManyErrors
+            )
+        {
+            self.finish_input();
+        }
+
+        Ok(self.headless_done)
+    }
+
+    /// 结束不使用终端界面的测试，返回测试的统计数据
+    ///
+    /// 没有输入完的单词不计入结果。已经结束的测试返回结束时的统计数据
+    pub fn finish_input(&mut self) -> TypingStats {
+        if !self.headless_done {
+            let state = match self.headless_state.take() {
+                Some(state) => state,
+                None => TestState::new(self.original_text(), self.config.case_insensitive),
+            };
+            self.stats.finish(
+                state.counted_chars(),
+                state.correct_chars(),
+                state.error_words(),
+            );
+            self.headless_state = Some(state);
+            self.headless_done = true;
+        }
+
+        self.stats.clone()
+    }
+
+    /// 不使用终端界面的测试中已经输入完的单词数，包括计时模式下之前添加的单词
+    pub fn completed_words(&self) -> usize {
+        self.headless_state
+            .as_ref()
+            .map_or(0, TestState::completed_words)
+    }
+
+    /// 不使用终端界面的测试中光标的位置，即整个测试中光标之前的字符数
+    pub fn current_char_index(&self) -> usize {
+        self.headless_state
+            .as_ref()
+            .map_or(0, |state| state.prev_pages_chars + state.input.len())
+    }
+
+    /// 光标位置上应该输入的字符
+    ///
+    /// 在 [`Typeing::run_with_input`] 之后是输入停下的位置，在那之前是第一个字符。
+    /// 已经输入完所有字符时返回 `None`
+    pub fn current_target_char(&self) -> Option<char> {
+        match &self.headless_state {
+            Some(state) => state.original_text.get(state.input.len()).copied(),
+            None => self.original_text().first().copied(),
+        }
+    }
+
+    /// 开始一次测试
+    ///
+    /// 返回测试结束之后用户选择的操作
+    pub fn test(&mut self) -> Result<ResultAction, TypeingError> {
+        if self.config.zen {
+            return self.zen_test();
+        }
+
+        let mut state = TestState::new(self.original_text(), self.config.case_insensitive);
+        let time_limit = self.config.time.map(Duration::from_secs);
+
+        let mut deadline: Option<Instant> = None;
+        let mut next_refresh: Option<Instant> = None;
+        if let Some(seconds) = self.config.countdown.filter(|&seconds| seconds > 0) {
+            if !self.countdown(seconds)? {
+                return Ok(ResultAction::Quit);
+            }
+
+            // 倒计时结束时开始计时，不计入用户的反应时间
+            self.stats.start();
+            let started_at = Instant::now();
+            deadline = time_limit.map(|time_limit| started_at + time_limit);
+            next_refresh = Some(started_at + STATUS_REFRESH_INTERVAL);
+        }
+        self.highlight_word(&state)?;
+        self.tui()?.flush()?;
+        let idle_timeout = self.config.idle_timeout.map(Duration::from_secs);
+        let mut last_key_at: Option<Instant> = None;
+        // 因为一段时间没有按键而自动暂停了计时
+        let mut idle = false;
+        let status = loop {
+            let now = Instant::now();
+            let idle_at = last_key_at
+                .zip(idle_timeout)
+                .map(|(at, timeout)| at + timeout);
+            if !idle && idle_at.is_some_and(|idle_at| now >= idle_at) {
+                idle = true;
+                self.stats.pause();
+                self.progress = "已暂停，按任意键继续".to_string();
+                self.display_status(self.stats.live_wpm(state.correct_chars()))?;
+                self.tui()?.move_to_cur_pos()?;
+                self.tui()?.flush()?;
+            }
+            if !idle && deadline.is_some_and(|deadline| now >= deadline) {
+                break TestStatus::TimeUp;
+            }
+            if !idle && next_refresh.is_some_and(|next_refresh| now >= next_refresh) {
+                // 刷新状态行的同时记录每秒的速度，用于计算一致性
+                self.stats.record_wpm_samples(state.correct_chars());
+                self.progress = self.progress_text(state.completed_words(), deadline);
+                let live_wpm = self.stats.live_wpm(state.correct_chars());
+                self.display_status(live_wpm)?;
+                self.tui()?.move_to_cur_pos()?;
+                self.tui()?.flush()?;
+                next_refresh = Some(now + STATUS_REFRESH_INTERVAL);
+            }
+
+            if self.tui()?.resized()? {
+                self.relayout(&state)?;
+            }
+
+            // 等待按键的同时，还需要按时刷新状态行和检查计时。暂停时只需要等待按键。
+            // 终端的大小改变时 KeyReader 也会返回，然后在循环的开头重新排列单词
+            let wake_at = [deadline, next_refresh, idle_at]
+                .into_iter()
+                .flatten()
+                .filter(|_| !idle)
+                .min();
+            let event = match wake_at {
+                Some(wake_at) => self
+                    .keys()?
+                    .next_event_timeout(wake_at.saturating_duration_since(now))?,
+                None => self.keys()?.next_event()?,
+            };
+            let key = match event {
+                Some(Event::Key(key)) => key,
+                Some(Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y))) => {
+                    if let Some(index) = self.tui()?.text_index_at(x, y) {
+                        self.process_click(&mut state, index)?;
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+
+            last_key_at = Some(Instant::now());
+            if std::mem::take(&mut idle) {
+                // 自动暂停的时间不计入计时模式的时间
+                let paused_for = self.stats.resume();
+                deadline = deadline.map(|deadline| deadline + paused_for);
+                next_refresh = next_refresh.map(|_| Instant::now());
+            }
+
+            if key == Key::Ctrl('p') {
+                match self.pause()? {
+                    // 暂停的时间不计入计时模式的时间
+                    Some(paused_for) => deadline = deadline.map(|deadline| deadline + paused_for),
+                    None => break TestStatus::Quit,
+                }
+                self.relayout(&state)?;
+                continue;
+            }
+
+            let status = self.process_key(&mut state, key)?;
+            if !matches!(status, TestStatus::NotDone) {
+                break status;
+            }
+
+            // 输入完一个单词时立即更新进度，不等到下一次刷新状态行
+            let progress = self.progress_text(state.completed_words(), deadline);
+            if progress != self.progress {
+                self.progress = progress;
+                let live_wpm = self.stats.live_wpm(state.correct_chars());
+                self.display_status(live_wpm)?;
+                self.tui()?.move_to_cur_pos()?;
+                self.tui()?.flush()?;
+            }
+
+            // 计时从第一次按键开始
+            if next_refresh.is_none() {
+                let started_at = Instant::now();
+                deadline = time_limit.map(|time_limit| started_at + time_limit);
+                next_refresh = Some(started_at + STATUS_REFRESH_INTERVAL);
+            }
+        };
+
+        match status {
+            TestStatus::Done
+            | TestStatus::TimeUp
+            | TestStatus::TooManyErrors
+            | TestStatus::FinishedEarly => {
+                self.too_many_errors = matches!(status, TestStatus::TooManyErrors);
+                self.finished_early = matches!(status, TestStatus::FinishedEarly);
+                self.stats.record_wpm_samples(state.correct_chars());
+                self.stats.finish(
+                    state.counted_chars(),
+                    state.correct_chars(),
+                    state.error_words(),
+                );
+                self.save_result(&state.missed_words());
+                match self.config.repeat {
+                    // 这一轮还没有结束时直接用新单词开始下一次测试
+                    Some(repeat) if self.session_results.len() < repeat => {
+                        Ok(ResultAction::NewWords)
+                    }
+                    Some(_) => self.display_summary(),
+                    None => self.display_results(&state.typed_words()),
+                }
+            }
+            TestStatus::Restart(action) => Ok(action),
+            // 中途退出时仍然显示这一轮中已经完成的测试
+            TestStatus::Quit if !self.session_results.is_empty() => self.display_summary(),
+            TestStatus::NotDone | TestStatus::PageDone | TestStatus::Quit => Ok(ResultAction::Quit),
+        }
+    }
+
+    /// `--zen` 模式的测试：没有需要输入的文本，接受任何字符，按 ctrl-d 结束
+    ///
+    /// 所有输入的字符都算作正确，速度按照结束时留下的字符数计算
+    fn zen_test(&mut self) -> Result<ResultAction, TypeingError> {
+        let mut typed = String::new();
+        self.stats = TypingStats::new(self.config.text_name(), 0, self.seed);
+        self.progress = "ctrl-d 结束".to_string();
+        self.display_zen(&typed)?;
+
+        let mut next_refresh: Option<Instant> = None;
+        loop {
+            let now = Instant::now();
+            if next_refresh.is_some_and(|next_refresh| now >= next_refresh) {
+                let chars = typed.chars().count();
+                self.stats.record_wpm_samples(chars);
+                self.display_zen(&typed)?;
+                next_refresh = Some(now + STATUS_REFRESH_INTERVAL);
+            }
+
+            let event = match next_refresh {
+                Some(next_refresh) => self
+                    .keys()?
+                    .next_event_timeout(next_refresh.saturating_duration_since(now))?,
+                None => self.keys()?.next_event()?,
+            };
+            let Some(Event::Key(key)) = event else {
+                if self.tui()?.resized()? {
+                    self.display_zen(&typed)?;
+                }
+                continue;
+            };
+            match key {
+                Key::Ctrl('d') => break,
+                Key::Ctrl('c') => return Ok(ResultAction::Quit),
+                Key::Ctrl('r') => return Ok(ResultAction::NewWords),
+                Key::Ctrl('t') => return Ok(ResultAction::Retry),
+                Key::Ctrl('p') => {
+                    if self.pause()?.is_none() {
+                        return Ok(ResultAction::Quit);
+                    }
+                }
+                Key::Backspace => {
+                    typed.pop();
+                }
+                Key::Char(c) => {
+                    let c = if c == '\n' || c == '\t' { ' ' } else { c };
+                    self.stats.start();
+                    self.stats.record_keystroke(c, true);
+                    typed.push(c);
+                    if next_refresh.is_none() {
+                        next_refresh = Some(Instant::now() + STATUS_REFRESH_INTERVAL);
+                    }
+                }
+                _ => continue,
+            }
+            self.display_zen(&typed)?;
+        }
+
+        let chars = typed.chars().count();
+        self.stats.record_wpm_samples(chars);
+        self.stats.finish(chars, chars, Vec::new());
+        self.save_result(&[]);
+        self.display_results(&[])
+    }
+
+    /// 重新显示 `--zen` 模式中输入的文本和状态行，光标在文本的末尾
+    fn display_zen(&mut self, typed: &str) -> Result<(), TypeingError> {
+        let live_wpm = self.stats.live_wpm(typed.chars().count());
+        let line_width_pct = self.config.line_width_pct as usize;
+        let (terminal_width, _) = termion::terminal_size()?;
+        let width = std::cmp::max(terminal_width as usize * line_width_pct / 100, 1);
+        let chars: Vec<char> = typed.chars().collect();
+        let mut lines: Vec<Vec<Text>> = chars
+            .chunks(width)
+            .map(|line| vec![Text::from(line.iter().collect::<String>())])
+            .collect();
+        // 刚好填满一行时光标在下一行的开头
+        if chars.len().is_multiple_of(width) {
+            lines.push(vec![Text::from("")]);
+        }
+
+        self.tui()?.begin_batch();
+        self.tui()?.reset_screen()?;
+        self.display_status(live_wpm)?;
+        self.tui()?.display_lines(&lines)?;
+        self.tui()?.end_batch()?;
+
+        Ok(())
+    }
+
+    /// 在屏幕中间显示倒计时，结束之后重新显示单词
+    ///
+    /// 倒计时期间忽略除了 ctrl-c 以外的按键，按下 ctrl-c 时返回 `false`
+    fn countdown(&mut self, seconds: u64) -> Result<bool, TypeingError> {
+        self.tui()?.hide_cursor()?;
+
+        for remaining in (1..=seconds).rev() {
+            let tui = self.tui()?;
+            tui.reset_screen()?;
+            tui.display_lines(&[[Text::from(remaining.to_string())]])?;
+
+            let next_second = Instant::now() + Duration::from_secs(1);
+            loop {
+                let now = Instant::now();
+                if now >= next_second {
+                    break;
+                }
+                if let Some(Key::Ctrl('c')) = self
+                    .keys()?
+                    .next_key_timeout(next_second.saturating_duration_since(now))?
+                {
+                    self.tui()?.show_cursor()?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        self.tui()?.show_cursor()?;
+        self.show_page()?;
+
+        Ok(true)
+    }
+
+    /// 暂停测试，直到再次按下 ctrl-p
+    ///
+    /// 暂停期间停止计时，隐藏单词并显示暂停的提示，忽略除了 ctrl-p 和 ctrl-c 以外的按键。
+    /// 返回暂停的时间，调用者需要在继续之后重新显示已经输入的进度。按下 ctrl-c 时返回 `None`
+    fn pause(&mut self) -> Result<Option<Duration>, TypeingError> {
+        self.stats.pause();
+
+        let tui = self.tui()?;
+        tui.reset_screen()?;
+        tui.display_lines::<&[Text], _>(&[
+            &[Text::from("已暂停")],
+            &[
+                Text::from("ctrl-p").with_color(color::Blue),
+                Text::from(" 继续, ").with_faint(),
+                Text::from("ctrl-c").with_color(color::Blue),
+                Text::from(" 退出 ").with_faint(),
+            ],
+        ])?;
+        tui.hide_cursor()?;
+
+        loop {
+            match self.keys()?.next_key()? {
+                Key::Ctrl('p') => break,
+                Key::Ctrl('c') => {
+                    self.tui()?.show_cursor()?;
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        }
+
+        let paused_for = self.stats.resume();
+        self.tui()?.show_cursor()?;
+
+        Ok(Some(paused_for))
+    }
+
+    /// 终端大小改变之后重新排列单词，并恢复已经输入的进度
+    fn relayout(&mut self, state: &TestState) -> Result<(), TypeingError> {
+        // 清空屏幕和重新显示的内容一起刷新，以免闪烁
+        self.tui()?.begin_batch();
+        loop {
+            self.show_page()?;
+
+            // 单词之间总是用一个空格分隔，所以换行的位置改变不会影响字符的顺序
+            let blind = self.config.blind;
+            let ignore_case = self.config.case_insensitive;
+            let theme = self.theme;
+            let tui = self.tui()?;
+            for (i, (&typed, &target)) in state.input.iter().zip(&state.original_text).enumerate() {
+                if let Some(overflow) = state.overflows.get(&i) {
+                    let rest: String = state.original_text[i..].iter().collect();
+                    tui.display_overflow(&overflow_text(overflow, blind, &theme), &rest)?;
+                }
+                tui.display_raw_text(&typed_text(target, typed, ignore_case, blind, &theme))?;
+                tui.move_to_next_char()?;
+            }
+            if !state.current_overflow().is_empty() {
+                let rest: String = state.original_text[state.input.len()..].iter().collect();
+                tui.display_overflow(
+                    &overflow_text(state.current_overflow(), blind, &theme),
+                    &rest,
+                )?;
+            }
+            tui.flush()?;
+
+            // 滚动显示时，重新排列之后光标所在的行可能不在屏幕上
+            if !tui.scroll_to_cursor() {
+                break;
+            }
+        }
+
+        self.highlight_word(state)?;
+        self.tui()?.end_batch()?;
+
+        Ok(())
+    }
+
+    /// `--highlight-word` 时反转显示光标所在的单词中还没有输入的字符，`--block-caret` 时在光标上显示方块
+    ///
+    /// 已经输入的字符仍然显示为正确或者错误的颜色。不会刷新输出
+    fn highlight_word(&mut self, state: &TestState) -> Result<(), TypeingError> {
+        let highlight = self.config.highlight_word;
+        if self.config.block_caret {
+            if let Some(&target) = state.original_text.get(state.input.len()) {
+                // 光标在这个单词和它后面的空格中向左移动，移动到前一个单词时会重新显示所有单词
+                let mut ahead = state.current_word_rest();
+                ahead.extend(
+                    state
+                        .original_text
+                        .get(state.input.len() + ahead.chars().count()),
+                );
+                return self.tui()?.display_block_caret(&ahead, target, highlight);
+            }
+        }
+        if !highlight {
+            return Ok(());
+        }
+
+        self.tui()?
+            .display_untyped_ahead(&state.current_word_rest(), true)
+    }
+
+    /// 在测试的状态中处理一次按键，不会显示任何东西
+    ///
+    /// 终端界面和不使用终端界面的测试都经过这里，所以 `--max-errors` 在两种测试中的效果相同
+    fn handle_key(&mut self, state: &mut TestState, key: Key) -> (TestStatus, Vec<KeyEffect>) {
+        let (status, effects) = self.apply_key(state, key);
+        let max_errors = self.config.max_errors;
+        if matches!(status, TestStatus::NotDone)
+            && max_errors > 0
+            && state.uncorrected_errors() >= max_errors
+        {
+            return (TestStatus::TooManyErrors, effects);
+        }
+
+        (status, effects)
+    }
+
+    /// 一次按键对测试状态的改变，不检查 `--max-errors`，参见 [`Typeing::handle_key`]
+    fn apply_key(&mut self, state: &mut TestState, key: Key) -> (TestStatus, Vec<KeyEffect>) {
+        // 按键的位置对应练习的布局中的字符，快捷键不受影响
+        let key = match key {
+            Key::Char(c) => Key::Char(self.key_map.remap(c)),
+            key => key,
+        };
+        match key {
+            Key::Ctrl('c') => (TestStatus::Quit, Vec::new()),
+            Key::Ctrl('r') => (TestStatus::Restart(ResultAction::NewWords), Vec::new()),
+            Key::Ctrl('t') => (TestStatus::Restart(ResultAction::Retry), Vec::new()),
+            // 还没有输入任何字符时没有可以显示的结果
+            key if Some(key) == self.config.finish_key.key() => {
+                if state.input.is_empty() && state.prev_pages_chars == 0 {
+                    (TestStatus::NotDone, Vec::new())
+                } else {
+                    (TestStatus::FinishedEarly, Vec::new())
+                }
+            }
+            // 已经输入完这一页时没有可以输入的字符
+            Key::Char(_) if state.page_complete() => (TestStatus::NotDone, Vec::new()),
+            // 严格模式下，当前单词没有完全正确地输入时不接受空格
+            Key::Char(' ') if self.config.strict && !state.word_done_correctly() => {
+                self.stats.start();
+                self.stats.record_keystroke(' ', false);
+                (TestStatus::NotDone, vec![KeyEffect::Mistake])
+            }
+            // 输错时停下的模式下，输错的字符只算作一次错误的按键，光标不会移动
+            Key::Char(typed)
+                if self.config.stop_on_error
+                    && state
+                        .original_text
+                        .get(state.input.len())
+                        .is_some_and(|&target| !chars_match(target, typed, state.ignore_case)) =>
+            {
+                self.stats.start();
+                self.stats.record_keystroke(typed, false);
+                (TestStatus::NotDone, vec![KeyEffect::Mistake])
+            }
+            // 在应该输入空格的位置输入了其他字符，表示单词后面多输入了字符
+            Key::Char(typed)
+                if state.original_text.get(state.input.len()) == Some(&' ')
+                    && !typed.is_whitespace() =>
+            {
+                self.stats.start();
+                self.stats.record_keystroke(typed, false);
+
+                if state.current_overflow().len() >= MAX_OVERFLOW_CHARS {
+                    return (TestStatus::NotDone, vec![KeyEffect::Mistake]);
+                }
+                state
+                    .overflows
+                    .entry(state.input.len())
+                    .or_default()
+                    .push(typed);
+                (
+                    TestStatus::NotDone,
+                    vec![KeyEffect::Mistake, state.overflow_effect()],
+                )
+            }
+            Key::Char(typed) => {
+                self.stats.start();
+                state.input.push(typed);
+                let target = state.original_text[state.input.len() - 1];
+                let correct = chars_match(target, typed, state.ignore_case);
+                self.stats.record_keystroke(typed, correct);
+
+                let mut effects = Vec::new();
+                if !correct {
+                    effects.push(KeyEffect::Mistake);
+                }
+                effects.push(KeyEffect::Typed { target, typed });
+
+                // 同一个字符输错太多次时跳过这个单词
+                let max_failures = self.config.hide_live_errors_after;
+                if !correct && max_failures > 0 {
+                    let failures = state.failures.entry(state.input.len() - 1).or_default();
+                    *failures += 1;
+                    if *failures >= max_failures {
+                        effects.extend(state.skip_word());
+                    }
+                }
+
+                self.stats.start_word();
+                if let Some(word) = state.just_completed_word() {
+                    self.stats.finish_word(word);
+                }
+
+                let status = if !state.page_complete() {
+                    TestStatus::NotDone
+                } else if self.config.time.is_none() {
+                    TestStatus::Done
+                } else {
+                    // 计时模式下，在时间用完之前继续显示新的单词
+                    TestStatus::PageDone
+                };
+                (status, effects)
+            }
+            // 先删除多余字符
+            Key::Backspace if !state.current_overflow().is_empty() => {
+                let len = state.input.len();
+                state.overflows.entry(len).or_default().pop();
+                (TestStatus::NotDone, vec![state.overflow_effect()])
+            }
+            Key::Backspace => {
+                let min_len = state.min_input_len(self.config.free_backspace);
+                let len = state.input.len().saturating_sub(1).max(min_len);
+                (TestStatus::NotDone, state.delete_to(len))
+            }
+            Key::Ctrl('w') => {
+                let min_len = state.min_input_len(self.config.free_backspace);
+                let len = state.word_start(min_len);
+                (TestStatus::NotDone, state.rewind_to(len))
+            }
+            _ => (TestStatus::NotDone, Vec::new()),
+        }
+    }
+
+    /// 处理测试中的一次按键，并在屏幕上显示改变，参见 [`Typeing::display_effects`]
+    fn process_key(&mut self, state: &mut TestState, key: Key) -> Result<TestStatus, TypeingError> {
+        let (status, effects) = self.handle_key(state, key);
+        self.display_effects(state, status, effects)
+    }
+
+    /// 处理 `--mouse` 时对第 `index` 个字符的点击，并在屏幕上显示改变
+    ///
+    /// 点击已经输入的单词时删除到这个单词的开头，但是不能删除退格键不能删除的字符。
+    /// 点击还没有输入的字符时什么也不做
+    fn process_click(&mut self, state: &mut TestState, index: usize) -> Result<(), TypeingError> {
+        if index >= state.input.len() {
+            return Ok(());
+        }
+
+        let min_len = state.min_input_len(self.config.free_backspace);
+        let len = state.word_start_at(index).max(min_len);
+        let effects = state.rewind_to(len);
+        self.display_effects(state, TestStatus::NotDone, effects)?;
+
+        Ok(())
+    }
+
+    /// 在屏幕上显示一次按键造成的改变，返回处理换页和滚动之后测试的状态
+    ///
+    /// 只重绘改变的字符（多余字符会重绘这一行剩下的部分），所有改变只刷新一次输出，
+    /// 以免在高延迟的终端（例如 SSH）上闪烁。只有换页、滚动和终端大小改变时才会重新显示所有单词
+    fn display_effects(
+        &mut self,
+        state: &mut TestState,
+        status: TestStatus,
+        effects: Vec<KeyEffect>,
+    ) -> Result<TestStatus, TypeingError> {
+        let blind = self.config.blind;
+        let ignore_case = self.config.case_insensitive;
+        let theme = self.theme;
+        let on_error = self.config.on_error;
+        let tui = self.tui()?;
+        let caret_from = tui.caret_pos();
+        // 下一个显示的文本是否需要闪烁
+        let mut flash = false;
+        for effect in effects {
+            match effect {
+                KeyEffect::Mistake => match on_error {
+                    OnError::None => {}
+                    OnError::Bell => tui.bell()?,
+                    OnError::Flash => flash = true,
+                },
+                KeyEffect::Typed { target, typed } => {
+                    let text = typed_text(target, typed, ignore_case, blind, &theme);
+                    if std::mem::take(&mut flash) {
+                        tui.display_raw_text(&text.clone().with_reverse())?;
+                        tui.flush()?;
+                        std::thread::sleep(ERROR_FLASH_DURATION);
+                        tui.move_to_cur_pos()?;
+                    }
+                    tui.display_raw_text(&text)?;
+                    tui.move_to_next_char()?;
+                }
+                KeyEffect::Deleted { target } => {
+                    tui.replace_text(theme.untyped(Text::from(target)))?;
+                }
+                KeyEffect::Overflow { overflow, rest } => {
+                    let text = overflow_text(&overflow, blind, &theme);
+                    if std::mem::take(&mut flash) {
+                        tui.display_overflow(&text.clone().with_reverse(), &rest)?;
+                        tui.flush()?;
+                        std::thread::sleep(ERROR_FLASH_DURATION);
+                    }
+                    tui.display_overflow(&text, &rest)?;
+                }
+            }
+        }
+        tui.animate_caret(caret_from)?;
+
+        // 回到了前一个单词时，之前突出显示的单词需要恢复，重新显示所有单词比逐个字符恢复更简单
+        let word_start = state.word_start_at(state.input.len());
+        let left_word = (self.config.highlight_word || self.config.block_caret)
+            && word_start < state.highlighted_word;
+        state.highlighted_word = word_start;
+
+        let status = match status {
+            TestStatus::PageDone => self.turn_page(state)?,
+            // 计时模式下输入到最后一行时在后面添加新的单词，不需要换页
+            TestStatus::NotDone if self.config.time.is_some() && self.tui()?.on_last_line() => {
+                self.refill(state)?;
+                TestStatus::NotDone
+            }
+            TestStatus::NotDone if left_word => {
+                self.relayout(state)?;
+                TestStatus::NotDone
+            }
+            // 滚动显示时光标移动到了另一行，重新显示窗口中的行
+            TestStatus::NotDone if self.tui()?.scroll_to_cursor() => {
+                self.relayout(state)?;
+                TestStatus::NotDone
+            }
+            TestStatus::NotDone => {
+                self.highlight_word(state)?;
+                TestStatus::NotDone
+            }
+            status => status,
+        };
+
+        self.tui()?.flush()?;
+
+        Ok(status)
+    }
+
+    /// 把测试结果保存到历史记录
+    ///
+    /// `missed_words` 是测试结束时仍然输错的单词，参见 [`TestState::missed_words`]
+    fn save_result(&mut self, missed_words: &[String]) {
+        // 历史记录只是附加的功能，写入失败（例如配置目录不可写）不应该中断测试。
+        // 太短的测试的速度没有意义，不保存，以免影响最好速度
+        self.wpm_percentile = None;
+        if !self.stats.too_short() {
+            // 与这次测试之前的记录比较，所以在保存之前读取
+            if self.config.results == ResultsLevel::Detailed {
+                self.wpm_percentile = history::TypingHistory::load().percentile(self.stats.wpm());
+            }
+            let _ = history::append_result(&self.stats, missed_words);
+        }
+        self.last_result = Some(self.stats.clone());
+        if self.config.repeat.is_some() {
+            self.session_results.push(self.stats.clone());
+        }
+        if let Some(on_complete) = &mut self.on_complete {
+            on_complete(&self.stats);
+        }
+    }
+
+    /// 注册在每次测试完成时调用的函数，参数是这次测试的统计数据
+    ///
+    /// 在显示结果页面之前，在运行 [`Typeing::test`] 的线程中调用，所以不需要 `Send`。
+    /// 调用期间终端仍然处于原始模式，函数不应该向标准输出打印内容。
+    /// 中途退出或者重新开始的测试不会调用它。再次注册会替换之前的函数
+    pub fn on_complete(&mut self, callback: CompletionCallback) {
+        self.on_complete = Some(callback);
+    }
+
+    /// 最后一次完成的测试的统计数据
+    ///
+    /// 中途退出或者重新开始的测试不算完成
+    pub fn last_result(&self) -> Option<&TypingStats> {
+        self.last_result.as_ref()
+    }
+
+    /// 显示测试结果，并等待用户重新开始或者退出
+    ///
+    /// 盲打模式下还会显示输错的单词，`words` 是测试中输入完的单词
+    fn display_results(&mut self, words: &[TypedWord]) -> Result<ResultAction, TypeingError> {
+        let detailed = self.config.results == ResultsLevel::Detailed;
+        let mut lines = if self.stats.too_short() {
+            vec![vec![
+                Text::from("速度: "),
+                Text::from("测试太短，无法测量").with_faint(),
+            ]]
+        } else {
+            let mut lines = vec![match self.config.metric {
+                Metric::Wpm => vec![
+                    Text::from("速度: "),
+                    Text::from(format!("{:.1} wpm", self.stats.wpm())).with_color(color::Green),
+                    Text::from(" (每分钟单词数)"),
+                ],
+                Metric::Cpm => vec![
+                    Text::from("速度: "),
+                    Text::from(format!("{:.0} cpm", self.stats.cpm())).with_color(color::Green),
+                    Text::from(" (每分钟正确字符数)"),
+                ],
+            }];
+            if detailed {
+                lines.push(vec![
+                    Text::from("原始速度: "),
+                    Text::from(format!("{:.1} wpm", self.stats.raw_wpm())).with_faint(),
+                    Text::from(", 净速度: "),
+                    Text::from(format!("{:.1} wpm", self.stats.net_wpm())).with_faint(),
+                ]);
+            }
+            lines
+        };
+        lines.push(vec![
+            Text::from("准确率: "),
+            Text::from(format!("{:.1}%", self.stats.accuracy())).with_color(color::Blue),
+        ]);
+        lines.push(vec![
+            Text::from("用时: "),
+            Text::from(format!("{:.1} 秒", self.stats.duration().as_secs_f64()))
+                .with_color(color::Blue),
+        ]);
+        if detailed {
+            lines.push(vec![
+                Text::from("一致性: "),
+                Text::from(format!("{:.0}%", self.stats.consistency())).with_color(color::Blue),
+            ]);
+            lines.push(vec![
+                Text::from("最长连续正确: "),
+                Text::from(format!("{} 个字符", self.stats.longest_streak()))
+                    .with_color(color::Blue),
+            ]);
+            if let Some(percentile) = self.wpm_percentile {
+                lines.push(vec![
+                    Text::from("比之前 "),
+                    Text::from(format!("{:.0}%", percentile)).with_color(color::Green),
+                    Text::from(" 的测试更快"),
+                ]);
+            }
+            lines.push(vec![Text::from(format!("种子: {}", self.seed)).with_faint()]);
+            let slowest_keys = self.stats.slowest_keys(SLOWEST_KEYS_SHOWN);
+            if !slowest_keys.is_empty() {
+                let mut line = vec![Text::from("最慢的按键:")];
+                for (c, latency) in slowest_keys {
+                    line.push(self.theme.incorrect(Text::from(format!(" {}", c))));
+                    line.push(Text::from(format!(" {}ms", latency.as_millis())).with_faint());
+                }
+                lines.push(line);
+            }
+            let slowest_words = self.stats.slowest_words(SLOWEST_WORDS_SHOWN);
+            if !slowest_words.is_empty() {
+                let mut line = vec![Text::from("最慢的单词:")];
+                for (word, wpm) in slowest_words {
+                    line.push(self.theme.incorrect(Text::from(format!(" {}", word))));
+                    line.push(Text::from(format!(" {:.0} wpm", wpm)).with_faint());
+                }
+                lines.push(line);
+            }
+        }
+        if self.too_many_errors {
+            lines.push(vec![Text::from(format!(
+                "没有修正的错误达到了 {} 个，测试提前结束",
+                self.config.max_errors
+            ))
+            .with_color(color::Yellow)]);
+        }
+        if self.finished_early {
+            lines.push(vec![Text::from(
+                "测试提前结束，只统计了已经输入完的单词".to_string(),
+            )
+            .with_color(color::Yellow)]);
+        }
+        if self.config.blind {
+            lines.extend(mistyped_lines(words, &self.theme));
+        }
+        if detailed && !self.stats.error_words().is_empty() {
+            lines.extend(review_lines(words, self.stats.error_words(), &self.theme));
+        }
+        for warning in &self.warnings {
+            lines.push(vec![
+                Text::from("警告: ").with_color(color::Yellow),
+                Text::from(warning.as_str()).with_faint(),
+            ]);
+        }
+        lines.push(vec![
+            Text::from("n").with_color(color::Blue),
+            Text::from(" 新单词, ").with_faint(),
+            Text::from("r").with_color(color::Blue),
+            Text::from(" 重试, ").with_faint(),
+            Text::from("q").with_color(color::Blue),
+            Text::from(" 退出 ").with_faint(),
+        ]);
+
+        self.display_page_and_wait(&lines)
+    }
+
+    /// 显示 `--repeat` 的一轮测试的平均速度、最好速度和平均准确率，并等待用户重新开始或者退出
+    ///
+    /// 之后的测试属于新的一轮
+    fn display_summary(&mut self) -> Result<ResultAction, TypeingError> {
+        let results = std::mem::take(&mut self.session_results);
+        let count = results.len() as f64;
+        let average_wpm = results.iter().map(TypingStats::wpm).sum::<f64>() / count;
+        let best_wpm = results.iter().map(TypingStats::wpm).fold(0.0, f64::max);
+        let average_accuracy = results.iter().map(TypingStats::accuracy).sum::<f64>() / count;
+
+        let lines = vec![
+            vec![Text::from(format!(
+                "完成了 {} 次测试中的 {} 次",
+                self.config.repeat.unwrap_or(results.len()),
+                results.len()
+            ))],
+            vec![
+                Text::from("平均速度: "),
+                Text::from(format!("{:.1} wpm", average_wpm)).with_color(color::Green),
+            ],
+            vec![
+                Text::from("最好速度: "),
+                Text::from(format!("{:.1} wpm", best_wpm)).with_color(color::Green),
+            ],
+            vec![
+                Text::from("平均准确率: "),
+                Text::from(format!("{:.1}%", average_accuracy)).with_color(color::Blue),
+            ],
+            vec![
+                Text::from("n").with_color(color::Blue),
+                Text::from(" 再来一轮, ").with_faint(),
+                Text::from("q").with_color(color::Blue),
+                Text::from(" 退出 ").with_faint(),
+            ],
+        ];
+
+        self.display_page_and_wait(&lines)
+    }
+
+    /// 清空屏幕显示 `lines`，然后等待用户选择下一步
+    ///
+    /// `n` 或者 ctrl-r 用新单词开始，`r` 或者 ctrl-t 重试同一组单词，`q` 或者 ctrl-c 退出。
+    /// 测试中的快捷键在这里仍然有效
+    fn display_page_and_wait(&mut self, lines: &[Vec<Text>]) -> Result<ResultAction, TypeingError> {
+        let tui = self.tui()?;
+        tui.reset_screen()?;
+        tui.display_lines(lines)?;
+        // 结果页面不显示光标
+        tui.hide_cursor()?;
+
+        let action = loop {
+            match self.keys()?.next_key()? {
+                Key::Char('n') | Key::Ctrl('r') => break ResultAction::NewWords,
+                Key::Char('r') | Key::Ctrl('t') => break ResultAction::Retry,
+                Key::Char('q') | Key::Ctrl('c') => break ResultAction::Quit,
+                _ => {}
+            }
+        };
+
+        self.tui()?.show_cursor()?;
+
+        Ok(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::TypeingConfigBuilder;
+    use keymap::FinishKey;
+    use textgen::IterSelector;
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    /// 不使用终端的测试，单词依次是 `words` 中的单词，用完之后从头开始
+    ///
+    /// 内置的单词列表可能没有编译进来，所以用总是可用的 `--ngram` 通过检查，然后替换选择器
+    fn typeing(config: TypeingConfigBuilder, words: &[&str]) -> Typeing {
+        let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+        let config = config.ngram(2).num_words(words.len()).build().unwrap();
+        Typeing::headless(config)
+            .unwrap()
+            .with_word_selector(Box::new(IterSelector::new(words.into_iter().cycle())))
+            .unwrap()
+    }
+
+    /// 依次处理 `keys` 中的每个字符，返回最后一次按键之后的状态
+    fn press(typeing: &mut Typeing, state: &mut TestState, keys: &str) -> TestStatus {
+        let mut status = TestStatus::NotDone;
+        for c in keys.chars() {
+            status = typeing.handle_key(state, Key::Char(c)).0;
+        }
+        status
+    }
+
+    /// 在 `state` 中直接输入 `typed`，不经过按键处理
+    fn type_into(state: &mut TestState, typed: &str) {
+        state.input.extend(typed.chars());
+    }
+
+    #[test]
+    fn mistyped_space_target_is_inverted() {
+        let theme = Theme::default();
+        let text = typed_text(' ', '\t', false, false, &theme).to_string();
+
+        assert!(text.contains(&termion::style::Invert.to_string()));
+        assert!(text.contains(' '));
+    }
+
+    #[test]
+    fn space_typed_over_letter_is_underlined() {
+        let theme = Theme::default();
+        let text = typed_text('a', ' ', false, false, &theme).to_string();
+
+        assert!(text.contains(&termion::style::Underline.to_string()));
+        assert!(text.contains('a'));
+    }
+
+    #[test]
+    fn space_over_letter_is_shown_as_mistyped_whitespace() {
+        let mut typeing = typeing(TypeingConfig::builder(), &["ab", "cd"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+        press(&mut typeing, &mut state, "a");
+
+        let (_, effects) = typeing.handle_key(&mut state, Key::Char(' '));
+        let typed = effects.iter().find_map(|effect| match effect {
+            KeyEffect::Typed { target, typed } => Some((*target, *typed)),
+            _ => None,
+        });
+        assert_eq!(typed, Some(('b', ' ')));
+        assert!(matches!(effects[0], KeyEffect::Mistake));
+    }
+
+    #[test]
+    fn other_mistakes_are_not_underlined() {
+        let theme = Theme::default();
+        let text = typed_text('a', 'b', false, false, &theme).to_string();
+
+        assert!(!text.contains(&termion::style::Underline.to_string()));
+        assert!(!text.contains(&termion::style::Invert.to_string()));
+    }
+
+    #[test]
+    fn missed_words_come_from_every_page() {
+        let mut state = TestState::new(chars("ab cd "), false);
+        type_into(&mut state, "xb cd ");
+        state.turn_page(chars("ef gh"));
+        type_into(&mut state, "ef gx");
+
+        assert_eq!(state.error_words(), [0, 3]);
+        assert_eq!(state.missed_words(), ["ab", "gh"]);
+    }
+
+    #[test]
+    fn missed_words_keep_case_and_punctuation() {
+        let mut state = TestState::new(chars("Hello, world."), false);
+        type_into(&mut state, "hello, world.");
+
+        assert_eq!(state.missed_words(), ["Hello,"]);
+    }
+
+    #[test]
+    fn max_errors_ends_the_key_handling() {
+        let mut typeing = typeing(TypeingConfig::builder().max_errors(2), &["abc", "def"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+
+        assert!(matches!(
+            press(&mut typeing, &mut state, "ax"),
+            TestStatus::NotDone
+        ));
+        assert!(matches!(
+            press(&mut typeing, &mut state, "x"),
+            TestStatus::TooManyErrors
+        ));
+    }
+
+    #[test]
+    fn max_errors_ends_headless_runs() {
+        let mut typeing = typeing(TypeingConfig::builder().max_errors(2), &["abc", "def"]);
+        assert!(!typeing.feed_char('x').unwrap());
+        assert!(typeing.feed_char('x').unwrap());
+        // 测试结束之后的按键被忽略
+        assert!(typeing.feed_char('c').unwrap());
+    }
+
+    #[test]
+    fn max_errors_stops_run_with_input() {
+        let mut typeing = typeing(TypeingConfig::builder().max_errors(2), &["abcd", "efgh"]);
+        typeing.run_with_input("abcd exx gh".chars()).unwrap();
+        // 第二个错误之后的 `gh` 没有输入
+        assert_eq!(typeing.completed_words(), 1);
+        assert_eq!(typeing.current_char_index(), 8);
+    }
+
+    fn typed(state: &TestState) -> String {
+        state.input.iter().collect()
+    }
+
+    #[test]
+    fn ctrl_w_deletes_a_word_in_the_middle_of_a_line() {
+        let mut typeing = typeing(TypeingConfig::builder(), &["ab", "cd", "ef"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+        press(&mut typeing, &mut state, "xb cx");
+
+        let (_, effects) = typeing.handle_key(&mut state, Key::Ctrl('w'));
+        assert_eq!(typed(&state), "xb ");
+        let deleted: Vec<char> = effects
+            .iter()
+            .filter_map(|effect| match effect {
+                KeyEffect::Deleted { target } => Some(*target),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deleted, ['d', 'c']);
+
+        // 输错的单词没有被锁定，可以继续删除
+        typeing.handle_key(&mut state, Key::Ctrl('w'));
+        assert_eq!(typed(&state), "");
+    }
+
+    /// 输入 `ab c` 之后按两次 ctrl-w，返回剩下的输入
+    fn ctrl_w_twice(free_backspace: bool) -> String {
+        let config = TypeingConfig::builder().free_backspace(free_backspace);
+        let mut typeing = typeing(config, &["ab", "cd", "ef"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+        press(&mut typeing, &mut state, "ab c");
+        typeing.handle_key(&mut state, Key::Ctrl('w'));
+        typeing.handle_key(&mut state, Key::Ctrl('w'));
+        typed(&state)
+    }
+
+    #[test]
+    fn ctrl_w_keeps_correct_words_unless_free_backspace() {
+        assert_eq!(ctrl_w_twice(false), "ab ");
+        assert_eq!(ctrl_w_twice(true), "");
+    }
+
+    #[test]
+    fn strict_mode_rejects_a_premature_space() {
+        let mut typeing = typeing(TypeingConfig::builder().strict(true), &["abc", "def"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+        press(&mut typeing, &mut state, "ab");
+
+        let (status, effects) = typeing.handle_key(&mut state, Key::Char(' '));
+        assert!(matches!(status, TestStatus::NotDone));
+        assert!(matches!(effects[..], [KeyEffect::Mistake]));
+        assert_eq!(typed(&state), "ab");
+
+        // 输错的单词也不能用空格跳过
+        press(&mut typeing, &mut state, "x ");
+        assert_eq!(typed(&state), "abx");
+
+        // 修正之后空格才会前进到下一个单词
+        typeing.handle_key(&mut state, Key::Backspace);
+        press(&mut typeing, &mut state, "c ");
+        assert_eq!(typed(&state), "abc ");
+    }
+
+    #[test]
+    fn without_strict_mode_space_advances() {
+        let mut typeing = typeing(TypeingConfig::builder(), &["abc", "def"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+        press(&mut typeing, &mut state, "ab ");
+
+        assert_eq!(typed(&state), "ab ");
+    }
+
+    #[test]
+    fn repeated_wrong_keystrokes_skip_the_word() {
+        let config = TypeingConfig::builder().hide_live_errors_after(3);
+        let mut typeing = typeing(config, &["abc", "def"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+
+        for _ in 0..2 {
+            press(&mut typeing, &mut state, "x");
+            typeing.handle_key(&mut state, Key::Backspace);
+        }
+        assert_eq!(typed(&state), "");
+
+        let (_, effects) = typeing.handle_key(&mut state, Key::Char('x'));
+        assert_eq!(state.input, ['x', SKIPPED_CHAR, SKIPPED_CHAR, ' ']);
+        let skipped = effects
+            .iter()
+            .filter(|effect| {
+                matches!(
+                    effect,
+                    KeyEffect::Typed {
+                        typed: SKIPPED_CHAR,
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!(skipped, 2);
+
+        // 跳过的单词算作输错的单词，接着输入下一个单词
+        assert!(matches!(
+            press(&mut typeing, &mut state, "def"),
+            TestStatus::Done
+        ));
+        assert_eq!(state.missed_words(), ["abc"]);
+    }
+
+    #[test]
+    fn wrong_keystrokes_do_not_skip_when_disabled() {
+        let mut typeing = typeing(TypeingConfig::builder(), &["abc", "def"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+        for _ in 0..10 {
+            press(&mut typeing, &mut state, "x");
+            typeing.handle_key(&mut state, Key::Backspace);
+        }
+        press(&mut typeing, &mut state, "x");
+
+        assert_eq!(typed(&state), "x");
+    }
+
+    #[test]
+    fn last_word_ends_the_test_without_a_final_space() {
+        let mut typeing = typeing(TypeingConfig::builder(), &["ab", "cd"]);
+        assert_eq!(typeing.original_text(), chars("ab cd"));
+        let mut state = TestState::new(typeing.original_text(), false);
+
+        assert!(matches!(
+            press(&mut typeing, &mut state, "ab cd"),
+            TestStatus::Done
+        ));
+    }
+
+    #[test]
+    fn require_final_space_waits_for_the_space() {
+        let config = TypeingConfig::builder().require_final_space(true);
+        let mut typeing = typeing(config, &["ab", "cd"]);
+        assert_eq!(typeing.original_text(), chars("ab cd "));
+        let mut state = TestState::new(typeing.original_text(), false);
+
+        assert!(matches!(
+            press(&mut typeing, &mut state, "ab cd"),
+            TestStatus::NotDone
+        ));
+        assert!(matches!(
+            press(&mut typeing, &mut state, " "),
+            TestStatus::Done
+        ));
+    }
+
+    #[test]
+    fn error_words_flag_uncorrected_words() {
+        let mut typeing = typeing(TypeingConfig::builder(), &["ab", "cd", "ef", "gh", "ij"]);
+        // 第 2 个单词输错，第 3 个单词输错之后修正，第 4 个单词后面有多余字符
+        let stats = typeing
+            .run_with_input("ab cx ex\u{7f}f ghz ij".chars())
+            .unwrap();
+
+        assert_eq!(stats.error_words(), [1, 3]);
+    }
+
+    #[test]
+    fn error_words_count_across_pages() {
+        let mut state = TestState::new(chars("ab cd "), false);
+        type_into(&mut state, "ab cx ");
+        state.turn_page(chars("ef gh"));
+        type_into(&mut state, "ex gh");
+
+        assert_eq!(state.error_words(), [1, 2]);
+    }
+
+    /// 在 `Hello` 中输入 `hELLO`，返回输错的单词和正确的字符数
+    fn type_other_case(case_insensitive: bool) -> (Vec<usize>, usize) {
+        let config = TypeingConfig::builder().case_insensitive(case_insensitive);
+        let mut typeing = typeing(config, &["Hello"]);
+        let mut state = TestState::new(typeing.original_text(), case_insensitive);
+        press(&mut typeing, &mut state, "hELLO");
+
+        (state.error_words(), state.correct_chars())
+    }
+
+    #[test]
+    fn case_sensitive_by_default() {
+        assert_eq!(type_other_case(false), (vec![0], 0));
+        assert!(!chars_match('a', 'A', false));
+    }
+
+    #[test]
+    fn case_insensitive_accepts_either_case() {
+        assert_eq!(type_other_case(true), (vec![], 5));
+        assert!(chars_match('a', 'A', true));
+        assert!(chars_match('É', 'é', true));
+
+        // 仍然显示输入的字符
+        let text = typed_text('a', 'A', true, false, &Theme::default());
+        assert!(text.text().contains('A'));
+    }
+
+    #[test]
+    fn timed_headless_run_refills_at_the_boundary() {
+        let mut typeing = typeing(TypeingConfig::builder().time(60), &["ab", "cd"]);
+
+        for c in "ab cd".chars() {
+            assert!(!typeing.feed_char(c).unwrap());
+        }
+        // 输入完最后一个字符时在后面添加了新的单词，之前的单词和光标的位置不变
+        assert_eq!(typeing.words(), ["ab", "cd", "ab", "cd"]);
+        assert_eq!(typeing.current_char_index(), 5);
+        assert_eq!(typeing.current_target_char(), Some(' '));
+        // `cd` 后面的空格还没有输入
+        assert_eq!(typeing.completed_words(), 1);
+
+        for c in " ab c".chars() {
+            assert!(!typeing.feed_char(c).unwrap());
+        }
+        let stats = typeing.finish_input();
+        assert_eq!(typeing.completed_words(), 3);
+        assert!(stats.error_words().is_empty());
+    }
+
+    #[test]
+    fn page_turn_ends_the_test_when_words_run_out() {
+        let config = TypeingConfig::builder()
+            .ngram(2)
+            .num_words(2)
+            .time(60)
+            .build()
+            .unwrap();
+        let words = ["ab", "cd"].map(String::from);
+        let mut typeing = Typeing::headless(config)
+            .unwrap()
+            .with_word_selector(Box::new(IterSelector::new(words.into_iter())))
+            .unwrap();
+        let mut state = TestState::new(typeing.original_text(), false);
+
+        // 与终端界面中输入完一页时一样换页，没有更多单词时测试结束而不是显示空的页面
+        let status = press(&mut typeing, &mut state, "ab cd");
+        assert!(matches!(status, TestStatus::PageDone));
+        let status = typeing.turn_page(&mut state).unwrap();
+        assert!(matches!(status, TestStatus::Done));
+        assert_eq!(typeing.words(), ["ab", "cd"]);
+
+        // 输入完的页面上继续按键不会越界
+        assert!(matches!(
+            press(&mut typeing, &mut state, "x "),
+            TestStatus::NotDone
+        ));
+        assert_eq!(typed(&state), "ab cd");
+    }
+
+    #[test]
+    fn keys_on_an_empty_page_are_ignored() {
+        let config = TypeingConfig::builder().strict(true).stop_on_error(true);
+        let mut typeing = typeing(config, &["ab"]);
+        let mut state = TestState::new(Vec::new(), false);
+
+        assert!(matches!(
+            press(&mut typeing, &mut state, "a b"),
+            TestStatus::NotDone
+        ));
+        assert_eq!(typed(&state), "");
+    }
+
+    #[test]
+    fn timed_headless_run_ends_when_words_run_out() {
+        let config = TypeingConfig::builder()
+            .ngram(2)
+            .num_words(2)
+            .time(60)
+            .build()
+            .unwrap();
+        let words = ["ab", "cd"].map(String::from);
+        let mut typeing = Typeing::headless(config)
+            .unwrap()
+            .with_word_selector(Box::new(IterSelector::new(words.into_iter())))
+            .unwrap();
+
+        // 没有更多单词可以添加，`cd` 之后的按键被忽略
+        let stats = typeing.run_with_input("ab cd ab".chars()).unwrap();
+        assert_eq!(typeing.completed_words(), 2);
+        assert!(stats.error_words().is_empty());
+    }
+
+    #[test]
+    fn stop_on_error_does_not_advance_on_a_wrong_key() {
+        let config = TypeingConfig::builder().stop_on_error(true);
+        let mut typeing = typeing(config, &["ab", "cd"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+
+        let (status, effects) = typeing.handle_key(&mut state, Key::Char('x'));
+        assert!(matches!(status, TestStatus::NotDone));
+        assert!(matches!(effects[..], [KeyEffect::Mistake]));
+        assert_eq!(typed(&state), "");
+
+        // 输错的按键仍然计入准确率
+        press(&mut typeing, &mut state, "a");
+        assert_eq!(typed(&state), "a");
+        assert_eq!(typeing.stats.accuracy(), 50.0);
+    }
+
+    #[test]
+    fn wrong_key_advances_by_default() {
+        let mut typeing = typeing(TypeingConfig::builder(), &["ab", "cd"]);
+        let mut state = TestState::new(typeing.original_text(), false);
+
+        let (_, effects) = typeing.handle_key(&mut state, Key::Char('x'));
+        assert!(matches!(
+            effects[..],
+            [
+                KeyEffect::Mistake,
+                KeyEffect::Typed {
+                    target: 'a',
+                    typed: 'x'
+                }
+            ]
+        ));
+        assert_eq!(typed(&state), "x");
+        assert_eq!(typeing.stats.accuracy(), 0.0);
+    }
+
+    #[test]
+    fn finish_key_counts_only_completed_words() {
+        let mut typeing = typeing(TypeingConfig::builder(), &["ab", "cd", "ef"]);
+        // 还没有输入任何字符时没有可以统计的结果，按键被忽略
+        assert!(!typeing.feed_char('\u{1b}').unwrap());
+
+        for c in "ab cx e".chars() {
+            assert!(!typeing.feed_char(c).unwrap());
+        }
+        assert!(typeing.feed_char('\u{1b}').unwrap());
+
+        let stats = typeing.finish_input();
+        let state = typeing.headless_state.as_ref().unwrap();
+        // 没有输入完的 `e` 不计入结果，输错的 `cd` 仍然算作输错的单词
+        assert_eq!(state.counted_chars(), 6);
+        assert_eq!(typeing.completed_words(), 2);
+        assert_eq!(stats.error_words(), [1]);
+    }
+
+    /// 输入 `a` 之后按下 `key`，返回测试是否结束了
+    fn ends_early(finish_key: FinishKey, key: char) -> bool {
+        let config = TypeingConfig::builder().finish_key(finish_key);
+        let mut typeing = typeing(config, &["ab", "cd"]);
+        assert!(!typeing.feed_char('a').unwrap());
+        typeing.feed_char(key).unwrap()
+    }
+
+    #[test]
+    fn finish_key_can_be_rebound_or_disabled() {
+        assert!(ends_early(FinishKey::Tab, '\t'));
+        assert!(!ends_early(FinishKey::Tab, '\u{1b}'));
+        assert!(!ends_early(FinishKey::None, '\u{1b}'));
     }
 }
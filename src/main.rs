@@ -1,11 +1,131 @@
-use clap::Parser;
+use std::path::Path;
+
+use typeing::config::{Metric, OutputFormat, TypeingConfig};
+use typeing::history;
+use typeing::stats::TypingStats;
+use typeing::wordlists;
 use typeing::TypeingError;
-use typeing::config::TypeingConfig;
-use typeing::Typeing;
+use typeing::{ResultAction, Typeing};
 
 fn main() -> Result<(), TypeingError> {
-    let config = TypeingConfig::parse();
+    let config = TypeingConfig::load()?;
+
+    if config.debug_info {
+        print_debug_info();
+        return Ok(());
+    }
+
+    if config.stats {
+        print_history_stats();
+        return Ok(());
+    }
+
+    if let Some(path) = &config.export_csv {
+        history::export_csv(Path::new(path))
+            .map_err(|err| TypeingError::from(format!("无法导出历史记录到 {}: {}", path, err)))?;
+        return Ok(());
+    }
+
+    if config.drill_errors && history::missed_words().is_empty() {
+        println!("历史记录中还没有输错的单词，先完成几次测试再来练习吧");
+        return Ok(());
+    }
+
+    if config.print_words {
+        // 不使用终端界面，所以不会进入原始模式
+        let typeing = Typeing::headless(config)?;
+        println!("{}", typeing.words().join(" "));
+        return Ok(());
+    }
+
+    let output = config.output;
+    let metric = config.metric;
+    let append_result = config.append_result.clone();
     let mut typeing = Typeing::new(config)?;
-    
+
+    loop {
+        match typeing.test()? {
+            ResultAction::Quit => break,
+            ResultAction::NewWords => typeing.restart(false)?,
+            ResultAction::Retry => typeing.restart(true)?,
+        }
+    }
+
+    // 终端在 Typeing 被释放时才会退出原始模式，在那之前打印的内容会被打乱
+    let last_result = typeing.last_result().cloned();
+    drop(typeing);
+
+    if let (Some(path), Some(stats)) = (&append_result, &last_result) {
+        history::append_result_line(Path::new(path), &stats.to_json())
+            .map_err(|err| TypeingError::from(format!("无法把结果追加到 {}: {}", path, err)))?;
+    }
+    if let (Some(output), Some(stats)) = (output, last_result) {
+        print_result(output, metric, &stats);
+    }
+
     Ok(())
 }
+
+/// 打印版本、启用的特性和嵌入的单词列表的哈希
+///
+/// 同一个种子在不同的版本中得到不同的单词时，可以用哈希确认是不是单词列表改变了
+fn print_debug_info() {
+    println!("typeing {}", env!("CARGO_PKG_VERSION"));
+    // 编译时启用的 cargo 特性
+    let features: Vec<&str> = [("builtin-wordlists", cfg!(feature = "builtin-wordlists"))]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+    if features.is_empty() {
+        println!("特性: 无");
+    } else {
+        println!("特性: {}", features.join(", "));
+    }
+    for (name, contents) in wordlists::embedded_lists() {
+        println!("{}: {:08x}", name, wordlists::content_hash(contents) >> 32);
+    }
+}
+
+/// 打印测试结果
+fn print_result(output: OutputFormat, metric: Metric, stats: &TypingStats) {
+    match output {
+        OutputFormat::Text if stats.too_short() => {
+            println!("速度: 测试太短，无法测量");
+            println!("准确率: {:.1}%", stats.accuracy());
+            println!("单词列表: {}", stats.text_name());
+            println!("种子: {}", stats.seed());
+        }
+        OutputFormat::Text => {
+            match metric {
+                Metric::Wpm => println!("速度: {:.1} wpm", stats.wpm()),
+                Metric::Cpm => println!("速度: {:.0} cpm", stats.cpm()),
+            }
+            println!("原始速度: {:.1} wpm", stats.raw_wpm());
+            println!("净速度: {:.1} wpm", stats.net_wpm());
+            println!("准确率: {:.1}%", stats.accuracy());
+            println!("用时: {:.1} 秒", stats.duration().as_secs_f64());
+            println!("一致性: {:.0}%", stats.consistency());
+            println!("最长连续正确: {} 个字符", stats.longest_streak());
+            println!("单词列表: {}", stats.text_name());
+            println!("种子: {}", stats.seed());
+        }
+        OutputFormat::Json => println!("{}", stats.to_json()),
+    }
+}
+
+/// 打印历史记录中最好的和平均的速度
+fn print_history_stats() {
+    let history = history::load_history();
+
+    if history.is_empty() {
+        println!("还没有任何测试记录");
+        return;
+    }
+
+    let best_wpm = history.iter().map(|record| record.wpm).fold(0.0, f64::max);
+    let average_wpm = history.iter().map(|record| record.wpm).sum::<f64>() / history.len() as f64;
+
+    println!("测试次数: {}", history.len());
+    println!("最好速度: {:.1} wpm", best_wpm);
+    println!("平均速度: {:.1} wpm", average_wpm);
+}
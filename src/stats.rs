@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// 可以可靠地计算速度的最短测试时间，更短的测试中几个字符的误差就会让 WPM 变得很离谱
+pub const MIN_MEASURABLE_DURATION: Duration = Duration::from_secs(1);
+
+/// 一次打字测试的统计数据
+#[derive(Clone, Debug, Default)]
+pub struct TypingStats {
+    /// 单词列表或者文本文件的名称
+    text_name: String,
+    /// 测试中的单词数
+    num_words: usize,
+    /// 选择单词使用的随机数种子
+    seed: u64,
+    /// 第一次按键的时间
+    started_at: Option<Instant>,
+    /// 测试完成的时间
+    ended_at: Option<Instant>,
+    /// 暂停开始的时间，没有暂停时为 `None`
+    paused_at: Option<Instant>,
+    /// 之前所有暂停的总时间，不计入测试花费的时间
+    paused_total: Duration,
+    /// 测试结束时输入的字符数
+    chars_typed: usize,
+    /// 测试结束时输入正确的字符数
+    correct_chars: usize,
+    /// 测试结束时含有没有修正的错误的单词在测试中的位置，从小到大排列
+    error_words: Vec<usize>,
+    /// 正确的按键次数
+    correct_keystrokes: usize,
+    /// 所有的按键次数，包括之后用退格键修正的按键
+    total_keystrokes: usize,
+    /// 现在连续正确输入的字符数
+    current_streak: usize,
+    /// 测试中最多连续正确输入的字符数
+    longest_streak: usize,
+    /// 上一次按键的时间
+    last_keystroke_at: Option<Instant>,
+    /// 每个字符正确输入时与上一次按键之间的总时间和次数
+    key_latencies: HashMap<char, (Duration, u32)>,
+    /// 测试中每一秒的 WPM
+    wpm_samples: Vec<f64>,
+    /// 上一次记录样本时输入正确的字符数
+    sampled_chars: usize,
+    /// 正在输入的单词第一次按键的时间
+    word_started_at: Option<Instant>,
+    /// 每个输入完的单词，以及从它的第一次按键到输入完的时间
+    word_timings: Vec<(String, Duration)>,
+}
+
+impl TypingStats {
+    pub fn new(text_name: String, num_words: usize, seed: u64) -> Self {
+        Self {
+            text_name,
+            num_words,
+            seed,
+            ..Self::default()
+        }
+    }
+
+    /// 单词列表或者文本文件的名称
+    pub fn text_name(&self) -> &str {
+        &self.text_name
+    }
+
+    /// 测试中的单词数
+    pub fn num_words(&self) -> usize {
+        self.num_words
+    }
+
+    /// 选择单词使用的随机数种子
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 在第一次按键时开始计时
+    ///
+    /// 已经开始计时的情况下不做任何事
+    pub fn start(&mut self) {
+        if self.started_at.is_none() {
+            self.started_at = Some(Instant::now());
+        }
+    }
+
+    /// 暂停计时
+    ///
+    /// 还没有开始计时或者已经暂停的情况下不做任何事
+    pub fn pause(&mut self) {
+        if self.started_at.is_some() && self.paused_at.is_none() {
+            self.paused_at = Some(Instant::now());
+        }
+    }
+
+    /// 继续计时，返回这次暂停的时间
+    ///
+    /// 没有暂停的情况下返回 0
+    pub fn resume(&mut self) -> Duration {
+        let Some(paused_at) = self.paused_at.take() else {
+            return Duration::ZERO;
+        };
+        let paused_for = paused_at.elapsed();
+        self.paused_total += paused_for;
+        // 暂停的时间也不计入下一次按键的输入时间
+        self.last_keystroke_at = self
+            .last_keystroke_at
+            .map(|last_keystroke_at| last_keystroke_at + paused_for);
+        self.word_started_at = self
+            .word_started_at
+            .map(|word_started_at| word_started_at + paused_for);
+
+        paused_for
+    }
+
+    /// 在测试完成时停止计时，并记录输入的字符数、输入正确的字符数和没有修正的输错的单词的位置
+    ///
+    /// `error_words` 是这些单词在测试中的位置（从 0 开始），参见 [`TypingStats::error_words`]
+    pub fn finish(&mut self, chars_typed: usize, correct_chars: usize, error_words: Vec<usize>) {
+        self.resume();
+        self.ended_at = Some(Instant::now());
+        self.chars_typed = chars_typed;
+        self.correct_chars = correct_chars;
+        self.error_words = error_words;
+    }
+
+    /// 测试结束时含有没有修正的错误的单词在测试中的位置，从小到大排列
+    pub fn error_words(&self) -> &[usize] {
+        &self.error_words
+    }
+
+    /// 记录一次输入 `c` 的按键是否正确
+    ///
+    /// 退格键不会减少按键次数，因此准确率反映实际按下的每一个键。
+    /// 输错的按键会让连续正确的字符数从 0 开始重新计算，参见 [`TypingStats::longest_streak`]。
+    /// 正确输入的非空白字符还会记录与上一次按键之间的时间，参见 [`TypingStats::slowest_keys`]
+    pub fn record_keystroke(&mut self, c: char, correct: bool) {
+        let now = Instant::now();
+        self.total_keystrokes += 1;
+        if correct {
+            self.correct_keystrokes += 1;
+            self.current_streak += 1;
+            self.longest_streak = self.longest_streak.max(self.current_streak);
+
+            if let Some(last_keystroke_at) = self.last_keystroke_at.filter(|_| !c.is_whitespace()) {
+                let latency = self.key_latencies.entry(c).or_default();
+                latency.0 += now.duration_since(last_keystroke_at);
+                latency.1 += 1;
+            }
+        } else {
+            self.current_streak = 0;
+        }
+        self.last_keystroke_at = Some(now);
+    }
+
+    /// 在一个单词的第一次按键时开始为这个单词计时
+    ///
+    /// 这个单词已经开始计时的情况下不做任何事
+    pub fn start_word(&mut self) {
+        if self.word_started_at.is_none() {
+            self.word_started_at = Some(Instant::now());
+        }
+    }
+
+    /// 输入完单词 `word` 时记录从它的第一次按键到现在的时间，参见 [`TypingStats::slowest_words`]
+    pub fn finish_word(&mut self, word: String) {
+        if let Some(word_started_at) = self.word_started_at.take() {
+            self.word_timings.push((word, word_started_at.elapsed()));
+        }
+    }
+
+    /// 每个输入完的单词，以及从它的第一次按键到输入完的时间，按照输入的顺序排列
+    pub fn word_timings(&self) -> &[(String, Duration)] {
+        &self.word_timings
+    }
+
+    /// 速度最慢的 `n` 个单词，以及输入每个单词的 WPM，从慢到快排列
+    ///
+    /// 单词的 WPM 按照它的字符数和从第一次按键到输入完的时间计算。
+    /// 只有一次按键的单词没有可以计算的时间，不会被选中
+    pub fn slowest_words(&self, n: usize) -> Vec<(&str, f64)> {
+        let mut words: Vec<(&str, f64)> = self
+            .word_timings
+            .iter()
+            .filter(|(_, duration)| !duration.is_zero())
+            .map(|(word, duration)| {
+                let minutes = duration.as_secs_f64() / 60.0;
+                (word.as_str(), (word.chars().count() as f64 / 5.0) / minutes)
+            })
+            .collect();
+        words.sort_by(|a, b| a.1.total_cmp(&b.1));
+        words.truncate(n);
+
+        words
+    }
+
+    /// 测试中最多连续正确输入的字符数
+    pub fn longest_streak(&self) -> usize {
+        self.longest_streak
+    }
+
+    /// 平均输入时间最长的 `n` 个字符，以及它们的平均输入时间
+    ///
+    /// 输入时间是正确输入这个字符与上一次按键之间的时间，从长到短排列
+    pub fn slowest_keys(&self, n: usize) -> Vec<(char, Duration)> {
+        let mut keys: Vec<(char, Duration)> = self
+            .key_latencies
+            .iter()
+            .map(|(&c, &(total, count))| (c, total / count))
+            .collect();
+        keys.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        keys.truncate(n);
+
+        keys
+    }
+
+    /// 测试花费的时间
+    ///
+    /// 不包括暂停的时间，没有开始或者没有结束的测试返回 0。
+    /// 计时模式下从倒计时结束开始计算，所以等于设置的时间
+    pub fn duration(&self) -> Duration {
+        match (self.started_at, self.ended_at) {
+            (Some(started_at), Some(ended_at)) => ended_at
+                .duration_since(started_at)
+                .saturating_sub(self.paused_total),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// 测试是否太短，无法得到可信的速度，参见 [`MIN_MEASURABLE_DURATION`]
+    pub fn too_short(&self) -> bool {
+        self.duration() < MIN_MEASURABLE_DURATION
+    }
+
+    /// 测试花费的分钟数
+    fn minutes(&self) -> f64 {
+        self.duration().as_secs_f64() / 60.0
+    }
+
+    /// 从第一次按键到现在的时间
+    ///
+    /// 不包括暂停的时间，还没有开始的测试返回 0
+    pub fn elapsed(&self) -> Duration {
+        let Some(started_at) = self.started_at else {
+            return Duration::ZERO;
+        };
+        let paused = self.paused_total
+            + self
+                .paused_at
+                .map_or(Duration::ZERO, |paused_at| paused_at.elapsed());
+
+        started_at.elapsed().saturating_sub(paused)
+    }
+
+    /// 测试进行中的 WPM，按照到现在为止输入正确的字符数计算
+    pub fn live_wpm(&self, correct_chars: usize) -> f64 {
+        let minutes = self.elapsed().as_secs_f64() / 60.0;
+        if correct_chars == 0 || minutes <= 0.0 {
+            return 0.0;
+        }
+
+        (correct_chars as f64 / 5.0) / minutes
+    }
+
+    /// 记录测试中每一秒的 WPM，用于计算 [`TypingStats::consistency`]
+    ///
+    /// `correct_chars` 是到现在为止输入正确的字符数。应该在测试中定期调用，
+    /// 距离上一次调用经过了好几秒时，这段时间输入的字符平均分到每一秒中
+    pub fn record_wpm_samples(&mut self, correct_chars: usize) {
+        let seconds = self.elapsed().as_secs() as usize;
+        if seconds <= self.wpm_samples.len() {
+            return;
+        }
+
+        let missing = seconds - self.wpm_samples.len();
+        let chars = correct_chars.saturating_sub(self.sampled_chars) as f64 / missing as f64;
+        self.wpm_samples
+            .extend(std::iter::repeat_n(chars / 5.0 * 60.0, missing));
+        self.sampled_chars = correct_chars;
+    }
+
+    /// 一致性（百分比），100% 表示每一秒的速度都相同
+    ///
+    /// 即 `(1 - 变异系数) * 100`，变异系数是每秒 WPM 的标准差除以平均值，不会小于 0。
+    /// 少于两个样本或者没有输入正确的字符时返回 0
+    pub fn consistency(&self) -> f64 {
+        let n = self.wpm_samples.len() as f64;
+        let mean = self.wpm_samples.iter().sum::<f64>() / n;
+        if self.wpm_samples.len() < 2 || mean <= 0.0 {
+            return 0.0;
+        }
+
+        let variance = self
+            .wpm_samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let coefficient_of_variation = variance.sqrt() / mean;
+
+        (1.0 - coefficient_of_variation).max(0.0) * 100.0
+    }
+
+    /// 总 WPM（每分钟单词数）
+    ///
+    /// 按照惯例每 5 个字符算作一个单词，即 `(字符数 / 5) / 分钟数`。
+    /// 没有输入任何字符的测试返回 0
+    pub fn wpm(&self) -> f64 {
+        let minutes = self.minutes();
+        if self.chars_typed == 0 || minutes <= 0.0 {
+            return 0.0;
+        }
+
+        (self.chars_typed as f64 / 5.0) / minutes
+    }
+
+    /// CPM（每分钟输入正确的字符数）
+    ///
+    /// 没有输入正确的字符的测试返回 0
+    pub fn cpm(&self) -> f64 {
+        let minutes = self.minutes();
+        if self.correct_chars == 0 || minutes <= 0.0 {
+            return 0.0;
+        }
+
+        self.correct_chars as f64 / minutes
+    }
+
+    /// 原始 WPM，按照所有的按键计算，包括之后用退格键修正的按键
+    pub fn raw_wpm(&self) -> f64 {
+        let minutes = self.minutes();
+        if self.total_keystrokes == 0 || minutes <= 0.0 {
+            return 0.0;
+        }
+
+        (self.total_keystrokes as f64 / 5.0) / minutes
+    }
+
+    /// 净 WPM，从输入的单词数中减去没有修正的输错的单词数
+    ///
+    /// 即 `(字符数 / 5 - 输错的单词数) / 分钟数`，不会小于 0
+    pub fn net_wpm(&self) -> f64 {
+        let minutes = self.minutes();
+        if self.chars_typed == 0 || minutes <= 0.0 {
+            return 0.0;
+        }
+
+        let words = self.chars_typed as f64 / 5.0 - self.error_words.len() as f64;
+        words.max(0.0) / minutes
+    }
+
+    /// 准确率（百分比）
+    ///
+    /// 没有任何按键的测试返回 0
+    pub fn accuracy(&self) -> f64 {
+        if self.total_keystrokes == 0 {
+            return 0.0;
+        }
+
+        self.correct_keystrokes as f64 / self.total_keystrokes as f64 * 100.0
+    }
+
+    /// JSON 格式的测试结果，内容参见 [`TypingStats`] 的 [`Serialize`] 实现
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("测试结果总是可以序列化为 JSON")
+    }
+}
+
+/// 序列化的是测试结果，而不是计时用的内部状态
+///
+/// 包括 WPM、原始 WPM、净 WPM、CPM、准确率、一致性、最长连续正确字符数、输错的单词的位置、测试花费的秒数、单词数、单词列表和种子
+impl Serialize for TypingStats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut result = serializer.serialize_struct("TypingStats", 12)?;
+        result.serialize_field("wpm", &self.wpm())?;
+        result.serialize_field("raw_wpm", &self.raw_wpm())?;
+        result.serialize_field("net_wpm", &self.net_wpm())?;
+        result.serialize_field("cpm", &self.cpm())?;
+        result.serialize_field("accuracy", &self.accuracy())?;
+        result.serialize_field("consistency", &self.consistency())?;
+        result.serialize_field("longest_streak", &self.longest_streak)?;
+        result.serialize_field("error_words", &self.error_words)?;
+        result.serialize_field("duration", &self.duration().as_secs_f64())?;
+        result.serialize_field("num_words", &self.num_words)?;
+        result.serialize_field("wordlist", &self.text_name)?;
+        result.serialize_field("seed", &self.seed)?;
+        result.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_survives_json_round_trip() {
+        let stats = TypingStats::new("top250".to_string(), 10, u64::MAX);
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+
+        assert_eq!(json["seed"].as_u64(), Some(u64::MAX));
+    }
+
+    /// 把测试的时间设置为正好 `duration`，不需要真的等待
+    fn lasting(stats: &mut TypingStats, duration: Duration) {
+        let started_at = Instant::now();
+        stats.started_at = Some(started_at);
+        stats.ended_at = Some(started_at + duration);
+    }
+
+    #[test]
+    fn raw_and_net_wpm_from_known_keystrokes() {
+        let mut stats = TypingStats::new("top250".to_string(), 2, 0);
+        // 目标是 `abcd efgh`：第一个单词输错一个字符并且没有修正，
+        // 第二个单词输错两次之后用退格键修正
+        for (c, correct) in [
+            ('a', true),
+            ('x', false),
+            ('c', true),
+            ('d', true),
+            (' ', true),
+            ('e', true),
+            ('y', false),
+            ('z', false),
+            ('f', true),
+            ('g', true),
+            ('h', true),
+        ] {
+            stats.record_keystroke(c, correct);
+        }
+        stats.finish(9, 8, vec![0]);
+        lasting(&mut stats, Duration::from_secs(12));
+
+        // 11 次按键 / 5 / 0.2 分钟
+        assert!((stats.raw_wpm() - 11.0).abs() < 1e-9);
+        // 9 个字符 / 5 / 0.2 分钟
+        assert!((stats.wpm() - 9.0).abs() < 1e-9);
+        // (9 / 5 - 1 个输错的单词) / 0.2 分钟
+        assert!((stats.net_wpm() - 4.0).abs() < 1e-9);
+        assert!((stats.accuracy() - 8.0 / 11.0 * 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn net_wpm_is_never_negative() {
+        let mut stats = TypingStats::new("top250".to_string(), 2, 0);
+        stats.record_keystroke('x', false);
+        stats.finish(2, 0, vec![0, 1]);
+        lasting(&mut stats, Duration::from_secs(60));
+
+        assert_eq!(stats.net_wpm(), 0.0);
+    }
+
+    #[test]
+    fn streak_resets_on_error_and_keeps_the_longest() {
+        let mut stats = TypingStats::new("top250".to_string(), 2, 0);
+        for correct in [true, true, true, true, false, true, true] {
+            stats.record_keystroke('a', correct);
+        }
+        assert_eq!(stats.longest_streak(), 4);
+        assert_eq!(stats.current_streak, 2);
+
+        for _ in 0..5 {
+            stats.record_keystroke('a', true);
+        }
+        assert_eq!(stats.longest_streak(), 7);
+    }
+
+    #[test]
+    fn duration_is_the_time_between_start_and_end() {
+        let mut stats = TypingStats::new("top250".to_string(), 10, 0);
+        assert_eq!(stats.duration(), Duration::ZERO);
+
+        let started_at = Instant::now();
+        stats.started_at = Some(started_at);
+        // 还没有结束的测试
+        assert_eq!(stats.duration(), Duration::ZERO);
+
+        stats.ended_at = Some(started_at + Duration::from_millis(34_200));
+        assert_eq!(stats.duration(), Duration::from_millis(34_200));
+        assert!(!stats.too_short());
+
+        // 暂停的时间不计入
+        stats.paused_total = Duration::from_millis(4_200);
+        assert_eq!(stats.duration(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn very_short_tests_are_too_short() {
+        let mut stats = TypingStats::new("top250".to_string(), 10, 0);
+        lasting(&mut stats, MIN_MEASURABLE_DURATION / 2);
+
+        assert!(stats.too_short());
+    }
+
+    /// 现在之前 `secs` 秒的时刻
+    fn secs_ago(secs: u64) -> Instant {
+        Instant::now() - Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn paused_time_is_not_counted() {
+        let mut stats = TypingStats::new("top250".to_string(), 10, 0);
+        stats.started_at = Some(secs_ago(10));
+        stats.last_keystroke_at = Some(secs_ago(5));
+        stats.word_started_at = Some(secs_ago(6));
+        stats.paused_at = Some(secs_ago(4));
+        // 已经暂停时再次暂停不会改变暂停开始的时间
+        let paused_at = stats.paused_at;
+        stats.pause();
+        assert_eq!(stats.paused_at, paused_at);
+
+        let paused_for = stats.resume();
+        assert!(paused_for >= Duration::from_secs(4));
+        assert_eq!(stats.paused_total, paused_for);
+        // 上一次按键和当前单词的开始时间都向后移动了暂停的时间
+        let since_keystroke = stats.last_keystroke_at.unwrap().elapsed();
+        assert!(
+            since_keystroke >= Duration::from_secs(1) && since_keystroke < Duration::from_secs(2)
+        );
+        let since_word = stats.word_started_at.unwrap().elapsed();
+        assert!(since_word >= Duration::from_secs(2) && since_word < Duration::from_secs(3));
+
+        stats.finish(10, 10, Vec::new());
+        let duration = stats.duration();
+        assert!(duration >= Duration::from_secs(6) && duration < Duration::from_secs(7));
+    }
+
+    #[test]
+    fn pause_and_resume_do_nothing_when_not_needed() {
+        let mut stats = TypingStats::new("top250".to_string(), 10, 0);
+        // 还没有开始的测试不能暂停
+        stats.pause();
+        assert!(stats.paused_at.is_none());
+        // 没有暂停时继续不会增加暂停的时间
+        stats.start();
+        assert_eq!(stats.resume(), Duration::ZERO);
+        assert_eq!(stats.paused_total, Duration::ZERO);
+    }
+}
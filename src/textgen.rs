@@ -1,172 +1,890 @@
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Cursor, Seek, SeekFrom};
-use std::path::PathBuf;
-
-use bisection::bisect_right;
-use rand::prelude::ThreadRng;
-use rand::Rng;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rand::distributions::{Distribution, Uniform, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::wordlists::QuoteLength;
+
+/// 读取文件的内容，无效的 UTF-8 字节会被替换为 `U+FFFD`
+///
+/// 第二个值表示是否替换了任何字节，一些较老的单词列表使用 Latin-1 等编码
+pub fn read_to_string_lossy(path: &Path) -> Result<(String, bool), io::Error> {
+    let bytes = std::fs::read(path)?;
+    match String::from_utf8(bytes) {
+        Ok(contents) => Ok((contents, false)),
+        Err(err) => Ok((String::from_utf8_lossy(err.as_bytes()).into_owned(), true)),
+    }
+}
 
+/// 从单词池中随机选择单词
+///
+/// 只保留由 2 到 8 个字母组成的单词，并转换为小写
+///
+/// 默认使用 [`StdRng`]，也可以用 [`RawWordSelector::with_rng`] 使用其他的随机数生成器
 #[derive(Debug)]
-pub struct RawWordSelector<T> {
-    reader: BufReader<T>,
-    letter_pos: [u64; 26],
-    letter_lines_sum: [u64; 27],
+pub struct RawWordSelector<R = StdRng> {
+    /// 按照在单词列表中原来的顺序排列的单词
+    ///
+    /// 单词列表只在创建时解析一次，之后每次选择单词只需要选择位置
+    words: Vec<String>,
+    rng: R,
+    /// 是否按照单词在列表中的排名加权选择
+    weighted: bool,
+    /// 读取的文件中是否有被替换的无效 UTF-8 字节
+    replaced_invalid_utf8: bool,
 }
 
-impl<T: Seek + io::Read> RawWordSelector<T> {
-    pub fn new(mut reader: BufReader<T>) -> Result<Self, io::Error> {
-        let mut letter_pos = [0u64; 26];
-        let mut letter_lines = [0u64; 26];
-        let mut num_lines = 0;
-        let mut cur_letter = b'a' - 1;
-        let mut cur_pos = 0;
-        let mut buffer = String::new();
+impl RawWordSelector {
+    /// 读取单词列表文件，参见 [`RawWordSelector::from_str`]
+    ///
+    /// 无效的 UTF-8 字节会被替换，参见 [`RawWordSelector::replaced_invalid_utf8`]
+    pub fn from_path(word_list_path: PathBuf) -> Result<Self, io::Error> {
+        let (contents, replaced) = read_to_string_lossy(&word_list_path)?;
+        let mut selector = Self::from_str(&contents)?;
+        selector.replaced_invalid_utf8 = replaced;
+        Ok(selector)
+    }
+}
 
-        fn is_letter(char: u8) -> bool {
-            (b'a'..=b'z').contains(&char)
+impl<R: Rng> RawWordSelector<R> {
+    /// 用 `rng` 从 `pool` 中选择单词
+    ///
+    /// 与 [`RawWordSelector::from_str`] 不同，`pool` 中的单词按原样使用。
+    /// 这样可以使用其他的随机数生成器，例如测试中产生固定序列的生成器。
+    /// `pool` 为空时返回错误
+    ///
+    /// ```
+    /// use rand::rngs::mock::StepRng;
+    /// use typeing::textgen::{RawWordSelector, WordSelector};
+    ///
+    /// let pool = vec!["alpha".to_string(), "beta".to_string()];
+    /// let mut selector = RawWordSelector::with_rng(pool, StepRng::new(0, 0)).unwrap();
+    /// assert_eq!(selector.new_words(2).unwrap(), ["alpha", "alpha"]);
+    /// ```
+    pub fn with_rng(pool: Vec<String>, rng: R) -> Result<Self, io::Error> {
+        if pool.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "单词池中没有任何单词",
+            ));
         }
 
-        loop {
-            buffer.clear();
-            let len = reader.read_line(&mut buffer)?;
+        Ok(Self {
+            words: pool,
+            rng,
+            weighted: false,
+            replaced_invalid_utf8: false,
+        })
+    }
 
-            if len == 0 {
-                break;
-            }
+    /// 读取的单词列表文件中是否有无效的 UTF-8 字节
+    ///
+    /// 被替换的字符不是字母，所以包含它们的单词不会被选中
+    pub fn replaced_invalid_utf8(&self) -> bool {
+        self.replaced_invalid_utf8
+    }
 
-            let line = buffer.to_ascii_lowercase();
-            num_lines += 1;
-            let first_char = line.bytes().next().unwrap();
+    /// 把 `contents` 中的单词添加到单词池的最后，参见 [`RawWordSelector::from_str`]
+    ///
+    /// 已经在单词池中的单词也会再添加一次，这样它们会更经常被选中
+    pub fn with_extra_words(mut self, contents: &str) -> Result<Self, io::Error> {
+        let extra = <RawWordSelector>::from_str(contents)?;
+        self.words.extend(extra.words);
+        Ok(self)
+    }
 
-            if !is_letter(first_char) {
-                continue;
-            }
+    /// 按照单词在列表中的排名加权选择，越靠前的单词越容易被选中
+    ///
+    /// 按照 Zipf 定律，第 `i` 个单词（从 0 开始）被选中的概率约为 `1 / (i + 1)`。
+    /// 具体做法是在 `[0, ln(n + 1))` 中均匀地选择 `u`，然后取第 `floor(e^u) - 1` 个单词，
+    /// 所以第 `i` 个单词的概率正好是 `ln((i + 2) / (i + 1)) / ln(n + 1)`，其中 `n` 是单词数。
+    ///
+    /// 只有在单词列表按照词频排序时才有意义。`weighted` 为 `false` 时所有单词的概率相同
+    pub fn with_rank_weighting(mut self, weighted: bool) -> Self {
+        self.weighted = weighted;
+        self
+    }
+}
 
-            if cur_letter != first_char {
-                letter_pos[cur_pos] = reader.stream_position()?;
+impl<R: SeedableRng> RawWordSelector<R> {
+    /// 使用固定的种子选择单词，相同的种子和单词列表总是得到相同的单词序列
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = R::seed_from_u64(seed);
+        self
+    }
+}
 
-                letter_lines[cur_pos] = num_lines;
-                num_lines = 0;
+/// 把用空白分隔的单词作为单词池，不需要读取任何文件
+///
+/// 没有任何可以使用的单词时返回错误
+impl FromStr for RawWordSelector {
+    type Err = io::Error;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        let words: Vec<String> = contents
+            .split_whitespace()
+            // 按照字符数而不是字节数计算长度，带重音符号的字母也算作一个字母
+            .filter(|word| (2..=8).contains(&word.chars().count()))
+            .filter(|word| word.chars().all(char::is_alphabetic))
+            .map(str::to_lowercase)
+            .collect();
+
+        if words.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "单词列表中没有由 2 到 8 个字母组成的单词",
+            ));
+        }
 
-                // println!(
-                //     "{}, {}, {}, {}",
-                //     char::from(first_char),
-                //     cur_pos,
-                //     letter_pos[cur_pos],
-                //     letter_lines[cur_pos],
-                // );
+        Ok(Self {
+            words,
+            rng: StdRng::from_entropy(),
+            weighted: false,
+            replaced_invalid_utf8: false,
+        })
+    }
+}
 
-                cur_pos += 1;
-                cur_letter = first_char;
+pub trait WordSelector {
+    /// 选择下一个单词
+    fn new_word(&mut self) -> Result<String, io::Error>;
 
-                if cur_pos >= 26 {
-                    break;
-                }
-            }
-        }
-        // 第一个字母移动到最后
-        letter_lines.rotate_left(1);
-        letter_lines[25] = num_lines;
+    /// 一次选择 `num_words` 个单词，用于显示一页单词
+    ///
+    /// 默认调用 `num_words` 次 [`WordSelector::new_word`]，实现可以覆盖这个方法来一次完成选择
+    fn new_words(&mut self, num_words: usize) -> Result<Vec<String>, io::Error> {
+        (0..num_words).map(|_| self.new_word()).collect()
+    }
+}
+
+/// 加权选择时，在 `[0, ln(n + 1))` 中选择的 `u` 对应的单词排名，参见 [`RawWordSelector::with_rank_weighting`]
+fn weighted_rank(u: f64, num_words: usize) -> usize {
+    let rank = u.exp() as usize - 1;
+    // 防止浮点误差超出范围
+    rank.min(num_words - 1)
+}
+
+impl<R: Rng> WordSelector for RawWordSelector<R> {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let num_words = self.words.len();
+        let index = if self.weighted {
+            weighted_rank(
+                self.rng.gen_range(0.0..((num_words + 1) as f64).ln()),
+                num_words,
+            )
+        } else {
+            self.rng.gen_range(0..num_words)
+        };
 
-        let letter_lines_sum: [u64; 26] = letter_lines
+        Ok(self.words[index].clone())
+    }
+
+    /// 用同一个分布一次选出所有单词的位置，不需要为每个单词重新计算范围
+    fn new_words(&mut self, num_words: usize) -> Result<Vec<String>, io::Error> {
+        let pool_size = self.words.len();
+        let indices: Vec<usize> = if self.weighted {
+            Uniform::new(0.0, ((pool_size + 1) as f64).ln())
+                .sample_iter(&mut self.rng)
+                .take(num_words)
+                .map(|u| weighted_rank(u, pool_size))
+                .collect()
+        } else {
+            Uniform::new(0, pool_size)
+                .sample_iter(&mut self.rng)
+                .take(num_words)
+                .collect()
+        };
+
+        Ok(indices
             .into_iter()
-            .scan(0, |acc, x| {
-                *acc += x;
-                Some(*acc)
+            .map(|index| self.words[index].clone())
+            .collect())
+    }
+}
+
+/// 按照原来的顺序返回一段文本中的单词
+///
+/// 与随机选择单词的 [`RawWordSelector`] 不同，这里会保留标点和大小写，
+/// 以便练习真实的文章。单词用完之后会从头开始
+#[derive(Debug)]
+pub struct FileTextSelector {
+    words: Vec<String>,
+    next_word: usize,
+    /// 读取的文件中是否有被替换的无效 UTF-8 字节
+    replaced_invalid_utf8: bool,
+}
+
+impl FileTextSelector {
+    /// 把 `text` 按照空白分成单词
+    ///
+    /// 制表符和换行一样只是分隔单词的空白，不会出现在单词中，
+    /// 单词之间总是用一个空格显示，所以不需要展开制表符
+    pub fn new(text: &str) -> Result<Self, io::Error> {
+        let words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+
+        if words.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "文本中没有任何单词",
+            ));
+        }
+
+        Ok(Self {
+            words,
+            next_word: 0,
+            replaced_invalid_utf8: false,
+        })
+    }
+
+    /// 读取文本文件，无效的 UTF-8 字节会被替换为 `U+FFFD`
+    pub fn from_path(text_path: PathBuf) -> Result<Self, io::Error> {
+        let (text, replaced) = read_to_string_lossy(&text_path)?;
+        let mut selector = Self::new(&text)?;
+        selector.replaced_invalid_utf8 = replaced;
+        Ok(selector)
+    }
+
+    /// 读取的文本文件中是否有无效的 UTF-8 字节
+    pub fn replaced_invalid_utf8(&self) -> bool {
+        self.replaced_invalid_utf8
+    }
+}
+
+impl WordSelector for FileTextSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        if self.next_word >= self.words.len() {
+            self.next_word = 0;
+        }
+
+        let word = self.words[self.next_word].clone();
+        self.next_word += 1;
+
+        Ok(word)
+    }
+
+    /// 从上次停下的地方继续返回最多 `num_words` 个单词
+    ///
+    /// 文本剩下的单词不够时只返回剩下的单词，不会从头拼接
+    fn new_words(&mut self, num_words: usize) -> Result<Vec<String>, io::Error> {
+        if self.next_word >= self.words.len() {
+            self.next_word = 0;
+        }
+
+        let end = std::cmp::min(self.next_word + num_words, self.words.len());
+        let words = self.words[self.next_word..end].to_vec();
+        self.next_word = end;
+
+        Ok(words)
+    }
+}
+
+/// 每次选择一整句名言的 [`WordSelector`]
+///
+/// 名言按原样显示，保留其中的标点和大小写
+pub struct QuoteSelector {
+    quotes: Vec<Vec<String>>,
+    rng: StdRng,
+    /// [`WordSelector::new_word`] 正在返回的名言，以及下一个单词的位置
+    current: Option<(usize, usize)>,
+}
+
+impl QuoteSelector {
+    /// `text` 中的每一行是一句名言，`length` 为 `Some` 时只选择这个长度的名言
+    pub fn new(text: &str, length: Option<QuoteLength>) -> Result<Self, io::Error> {
+        let quotes: Vec<Vec<String>> = text
+            .lines()
+            .map(|line| {
+                line.split_whitespace()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
             })
-            .collect::<Vec<u64>>()
-            .try_into()
-            .unwrap();
-        let mut letter_lines_sum_ = [0u64; 27];
-        letter_lines_sum_[1..].copy_from_slice(&letter_lines_sum[..]);
-        let letter_lines_sum = letter_lines_sum_;
-
-        let word_selector = Self {
-            reader,
-            letter_pos,
-            letter_lines_sum,
+            .filter(|words| !words.is_empty())
+            .filter(|words| length.is_none_or(|length| length.contains(words.len())))
+            .collect();
+
+        if quotes.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "没有符合条件的名言",
+            ));
+        }
+
+        Ok(Self {
+            quotes,
+            rng: StdRng::from_entropy(),
+            current: None,
+        })
+    }
+
+    /// 使用固定的种子选择名言
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    fn random_quote(&mut self) -> usize {
+        self.rng.gen_range(0..self.quotes.len())
+    }
+}
+
+impl WordSelector for QuoteSelector {
+    /// 依次返回一句名言中的单词，返回完之后换一句新的名言
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let (quote, index) = match self.current {
+            Some((quote, index)) if index < self.quotes[quote].len() => (quote, index),
+            _ => (self.random_quote(), 0),
         };
 
-        Ok(word_selector)
+        self.current = Some((quote, index + 1));
+        Ok(self.quotes[quote][index].clone())
     }
 
-    fn word_at_letter_offset(
-        &mut self,
-        letter_index: usize,
-        line_offset: u64,
-    ) -> Result<String, io::Error> {
-        self.reader
-            .seek(SeekFrom::Start(self.letter_pos[letter_index]))?;
+    /// 返回一整句名言，忽略 `num_words`
+    fn new_words(&mut self, _num_words: usize) -> Result<Vec<String>, io::Error> {
+        let quote = self.random_quote();
+        self.current = None;
+
+        Ok(self.quotes[quote].clone())
+    }
+}
 
-        let mut buffer = String::new();
-        let mut line_no = 0;
+/// 一个单词结束一个句子的概率
+const SENTENCE_END_PROBABILITY: f64 = 0.15;
+/// 句子中间的单词后面加上逗号的概率
+const COMMA_PROBABILITY: f64 = 0.1;
+/// 句子中间的单词被引号包围的概率
+const QUOTE_PROBABILITY: f64 = 0.05;
+/// 句子结尾的标点，句号比问号和感叹号更常见
+const SENTENCE_ENDINGS: [char; 5] = ['.', '.', '.', '?', '!'];
+
+/// 给其他 [`WordSelector`] 选出的单词加上标点
+///
+/// 句子的第一个单词会首字母大写，最后一个单词后面加上句号、问号或者感叹号，
+/// 句子中间的单词偶尔会加上逗号或者被引号包围
+pub struct PunctuationSelector {
+    inner: Box<dyn WordSelector>,
+    /// 下一个单词是否是句子的开头
+    sentence_start: bool,
+    rng: StdRng,
+}
 
-        loop {
-            buffer.clear();
-            self.reader.read_line(&mut buffer)?;
+impl PunctuationSelector {
+    pub fn new(inner: Box<dyn WordSelector>) -> Self {
+        Self {
+            inner,
+            sentence_start: true,
+            rng: StdRng::from_entropy(),
+        }
+    }
 
-            if line_no == line_offset {
-                break;
+    /// 使用固定的种子添加标点
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl WordSelector for PunctuationSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let rng = &mut self.rng;
+        let mut word = self.inner.new_word()?;
+
+        if self.sentence_start {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                word = first.to_uppercase().chain(chars).collect();
             }
+        }
 
-            line_no += 1;
+        self.sentence_start = rng.gen_bool(SENTENCE_END_PROBABILITY);
+        if self.sentence_start {
+            word.push(SENTENCE_ENDINGS[rng.gen_range(0..SENTENCE_ENDINGS.len())]);
+        } else if rng.gen_bool(COMMA_PROBABILITY) {
+            word.push(',');
+        } else if rng.gen_bool(QUOTE_PROBABILITY) {
+            word = format!("\"{}\"", word);
         }
 
-        // 删除尾随换行符
-        buffer.truncate(buffer.len() - 1);
+        Ok(word)
+    }
+}
+
+/// 单词被替换为数字的概率
+const NUMBER_PROBABILITY: f64 = 0.2;
+/// 数字的最大位数
+const MAX_NUMBER_DIGITS: u32 = 4;
+
+/// 把其他 [`WordSelector`] 选出的一部分单词替换为 1 到 4 位的数字
+///
+/// 用来练习单词列表从来不会用到的数字键
+pub struct NumberSelector {
+    inner: Box<dyn WordSelector>,
+    rng: StdRng,
+}
 
-        Ok(buffer)
+impl NumberSelector {
+    pub fn new(inner: Box<dyn WordSelector>) -> Self {
+        Self {
+            inner,
+            rng: StdRng::from_entropy(),
+        }
     }
 
-    fn new_word_raw(&mut self, rng: &mut ThreadRng) -> Result<String, io::Error> {
-        let line_index = rng.gen_range(self.letter_lines_sum[0]..self.letter_lines_sum[26]);
-        let letter_lines_sum_index = bisect_right(&self.letter_lines_sum, &line_index);
-        let line_offset = self.letter_lines_sum[letter_lines_sum_index] - line_index;
-        let letter_index = letter_lines_sum_index - 1;
+    /// 使用固定的种子生成数字
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+}
 
-        self.word_at_letter_offset(letter_index, line_offset)
+impl WordSelector for NumberSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let rng = &mut self.rng;
+
+        if rng.gen_bool(NUMBER_PROBABILITY) {
+            let digits = rng.gen_range(1..=MAX_NUMBER_DIGITS);
+            // 只由数字组成，不会包含空格，所以不影响 `display_words` 的换行计算
+            Ok(rng.gen_range(0..10u32.pow(digits)).to_string())
+        } else {
+            self.inner.new_word()
+        }
     }
 }
 
-impl RawWordSelector<File> {
-    pub fn from_path(word_list_path: PathBuf) -> Result<Self, io::Error> {
-        let file = File::open(word_list_path)?;
-        let reader = BufReader::new(file);
-        Self::new(reader)
+/// 英语中常见的两个字母的组合和相对频率
+const BIGRAMS: &[(&str, u32)] = &[
+    ("th", 356),
+    ("he", 307),
+    ("in", 243),
+    ("er", 205),
+    ("an", 199),
+    ("re", 185),
+    ("on", 176),
+    ("at", 149),
+    ("en", 145),
+    ("nd", 135),
+    ("ti", 134),
+    ("es", 134),
+    ("or", 128),
+    ("te", 120),
+    ("of", 117),
+    ("ed", 117),
+    ("is", 113),
+    ("it", 112),
+    ("al", 109),
+    ("ar", 107),
+    ("st", 105),
+    ("to", 104),
+    ("nt", 104),
+    ("ng", 95),
+    ("se", 93),
+    ("ha", 93),
+    ("as", 87),
+    ("ou", 87),
+    ("io", 83),
+    ("le", 83),
+    ("ve", 83),
+    ("co", 79),
+    ("me", 79),
+    ("de", 76),
+    ("hi", 76),
+    ("ri", 73),
+    ("ro", 73),
+    ("ic", 70),
+    ("ne", 69),
+    ("ea", 69),
+    ("ra", 69),
+    ("ce", 65),
+    ("li", 62),
+    ("ch", 60),
+    ("ll", 58),
+    ("be", 58),
+    ("ma", 57),
+    ("si", 55),
+    ("om", 55),
+    ("ur", 54),
+];
+
+/// 英语中常见的三个字母的组合和相对频率
+const TRIGRAMS: &[(&str, u32)] = &[
+    ("the", 181),
+    ("and", 73),
+    ("ing", 72),
+    ("ent", 42),
+    ("ion", 42),
+    ("her", 36),
+    ("for", 34),
+    ("tha", 33),
+    ("nth", 33),
+    ("int", 32),
+    ("ere", 31),
+    ("tio", 31),
+    ("ter", 30),
+    ("est", 28),
+    ("ers", 28),
+    ("ati", 26),
+    ("hat", 26),
+    ("ate", 25),
+    ("all", 25),
+    ("eth", 24),
+    ("hes", 24),
+    ("ver", 24),
+    ("his", 24),
+    ("oft", 22),
+    ("ith", 21),
+    ("fth", 21),
+    ("sth", 21),
+    ("oth", 21),
+    ("res", 21),
+    ("ont", 20),
+];
+
+/// 按照英语中的频率随机选择两个或三个字母的组合，例如 `th` 和 `ing`，用来练习手指在按键之间的移动
+///
+/// 每个组合都当作一个单词显示和计算速度
+pub struct NgramSelector {
+    ngrams: &'static [(&'static str, u32)],
+    weights: WeightedIndex<u32>,
+    rng: StdRng,
+}
+
+impl NgramSelector {
+    /// `length` 是每个组合的字母数，只能是 2 或 3
+    pub fn new(length: usize) -> Result<Self, io::Error> {
+        let ngrams = match length {
+            2 => BIGRAMS,
+            3 => TRIGRAMS,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("字母组合的长度只能是 2 或 3，得到 {}", length),
+                ))
+            }
+        };
+        let weights = WeightedIndex::new(ngrams.iter().map(|&(_, weight)| weight))
+            .expect("内置的字母组合的频率都大于 0");
+
+        Ok(Self {
+            ngrams,
+            weights,
+            rng: StdRng::from_entropy(),
+        })
+    }
+
+    /// 使用固定的种子选择字母组合
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl WordSelector for NgramSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let index = self.weights.sample(&mut self.rng);
+        Ok(self.ngrams[index].0.to_string())
     }
 }
 
-impl RawWordSelector<Cursor<String>> {
-    pub fn from_string(word_list: String) -> Result<Self, io::Error> {
-        let cursor = Cursor::new(word_list);
-        let reader = BufReader::new(cursor);
+/// 从任意的迭代器中依次取出单词，例如生成器、网络流或者数据库游标
+///
+/// 这是把 Typeing 嵌入到其他程序中时提供单词的方式，参见 [`crate::Typeing::with_word_selector`]。
+/// 迭代器在取够单词之前结束时，只返回已经取出的单词，并记录少了多少个，参见 [`IterSelector::shortfall`]
+///
+/// ```
+/// use typeing::textgen::{IterSelector, WordSelector};
+///
+/// let mut n = 0;
+/// let mut selector = IterSelector::new(std::iter::repeat_with(move || {
+///     n += 1;
+///     format!("word{}", n)
+/// }));
+/// assert_eq!(selector.new_words(2).unwrap(), ["word1", "word2"]);
+/// ```
+pub struct IterSelector<I> {
+    iter: I,
+    /// 迭代器结束时还没有取到的单词数
+    shortfall: usize,
+}
+
+impl<I: Iterator<Item = String>> IterSelector<I> {
+    pub fn new(iter: I) -> Self {
+        Self { iter, shortfall: 0 }
+    }
 
-        RawWordSelector::new(reader)
+    /// 因为迭代器结束而没有取到的单词数，每次 [`WordSelector::new_words`] 的差额会累加起来
+    pub fn shortfall(&self) -> usize {
+        self.shortfall
     }
 }
 
-pub trait WordSelector {
-    fn new_word(&mut self) -> Result<String, io::Error>;
+impl<I: Iterator<Item = String>> WordSelector for IterSelector<I> {
+    /// 迭代器已经结束时返回 [`io::ErrorKind::UnexpectedEof`] 错误
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        self.iter.next().ok_or_else(|| {
+            self.shortfall += 1;
+            io::Error::new(io::ErrorKind::UnexpectedEof, "单词已经用完")
+        })
+    }
+
+    /// 最多取出 `num_words` 个单词，迭代器结束时返回的单词会更少
+    fn new_words(&mut self, num_words: usize) -> Result<Vec<String>, io::Error> {
+        let words: Vec<String> = self.iter.by_ref().take(num_words).collect();
+        self.shortfall += num_words - words.len();
+
+        Ok(words)
+    }
+}
+
+/// [`NoRepeatSelector`] 选到与上一个单词相同的单词时最多重新选择的次数
+const MAX_REPEAT_RETRIES: usize = 10;
+
+/// 避免其他 [`WordSelector`] 连续两次选出同一个单词
+///
+/// 选到与上一个单词相同的单词时重新选择，最多 [`MAX_REPEAT_RETRIES`] 次，
+/// 所以只有一个单词的列表仍然可以使用
+pub struct NoRepeatSelector {
+    inner: Box<dyn WordSelector>,
+    /// 上一个选出的单词
+    prev_word: Option<String>,
+}
+
+impl NoRepeatSelector {
+    pub fn new(inner: Box<dyn WordSelector>) -> Self {
+        Self {
+            inner,
+            prev_word: None,
+        }
+    }
+}
+
+impl NoRepeatSelector {
+    /// 如果 `word` 与上一个单词相同就重新选择，然后把结果记为上一个单词
+    fn avoid_repeat(&mut self, mut word: String) -> Result<String, io::Error> {
+        for _ in 0..MAX_REPEAT_RETRIES {
+            if self.prev_word.as_ref() != Some(&word) {
+                break;
+            }
+            word = self.inner.new_word()?;
+        }
+        self.prev_word = Some(word.clone());
 
+        Ok(word)
+    }
+}
+
+impl WordSelector for NoRepeatSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let word = self.inner.new_word()?;
+        self.avoid_repeat(word)
+    }
+
+    /// 让内部的选择器一次选出所有单词，只重新选择与前一个单词相同的单词
     fn new_words(&mut self, num_words: usize) -> Result<Vec<String>, io::Error> {
-        (0..num_words)
+        self.inner
+            .new_words(num_words)?
             .into_iter()
-            .map(|_| self.new_word())
+            .map(|word| self.avoid_repeat(word))
             .collect()
     }
 }
 
-impl<T: Seek + io::Read> WordSelector for RawWordSelector<T> {
+/// 按照权重从多个 [`WordSelector`] 中随机选择单词
+///
+/// 每个单词都独立地按权重选择来源，所以来自不同列表的单词是随机交错的
+pub struct CombinedSelector {
+    selectors: Vec<Box<dyn WordSelector>>,
+    weights: WeightedIndex<u32>,
+    rng: StdRng,
+}
+
+impl CombinedSelector {
+    /// `weights` 与 `selectors` 一一对应，至少要有一个权重大于 0
+    pub fn new(
+        selectors: Vec<Box<dyn WordSelector>>,
+        weights: Vec<u32>,
+    ) -> Result<Self, io::Error> {
+        if selectors.len() != weights.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "有 {} 个单词列表，但是有 {} 个权重",
+                    selectors.len(),
+                    weights.len()
+                ),
+            ));
+        }
+
+        let weights = WeightedIndex::new(weights).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("无效的权重: {}", err))
+        })?;
+
+        Ok(Self {
+            selectors,
+            weights,
+            rng: StdRng::from_entropy(),
+        })
+    }
+
+    /// 使用固定的种子选择单词的来源
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl WordSelector for CombinedSelector {
+    fn new_word(&mut self) -> Result<String, io::Error> {
+        let index = self.weights.sample(&mut self.rng);
+        self.selectors[index].new_word()
+    }
+}
+
+/// 把其他 [`WordSelector`] 选出的一部分单词首字母大写
+///
+/// 不是以字母开头的单词（例如数字）保持不变
+pub struct CapitalizeSelector {
+    inner: Box<dyn WordSelector>,
+    /// 首字母大写的单词所占的比例
+    ratio: f64,
+    rng: StdRng,
+}
+
+impl CapitalizeSelector {
+    /// `ratio` 必须在 0 到 1 之间
+    pub fn new(inner: Box<dyn WordSelector>, ratio: f64) -> Self {
+        Self {
+            inner,
+            ratio,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// 使用固定的种子选择要大写的单词
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl WordSelector for CapitalizeSelector {
     fn new_word(&mut self) -> Result<String, io::Error> {
-        let mut rng = rand::thread_rng();
-        let mut word = "-".to_string();
+        let word = self.inner.new_word()?;
+
+        if !self.rng.gen_bool(self.ratio) {
+            return Ok(word);
+        }
 
-        while word.len() < 2 || word.len() > 8 || !word.chars().all(|c| c.is_ascii_alphabetic()) {
-            word = self.new_word_raw(&mut rng)?;
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) if first.is_alphabetic() => Ok(first.to_uppercase().chain(chars).collect()),
+            _ => Ok(word),
         }
+    }
+}
 
-        word.make_ascii_lowercase();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::{layout_words, HasLength, Text};
 
-        Ok(word)
+    fn two_word_pool(seed: u64) -> Box<dyn WordSelector> {
+        let pool = vec!["foo".to_string(), "bar".to_string()];
+        Box::new(RawWordSelector::with_rng(pool, StdRng::seed_from_u64(seed)).unwrap())
+    }
+
+    fn has_adjacent_repeats(words: &[String]) -> bool {
+        words.windows(2).any(|pair| pair[0] == pair[1])
+    }
+
+    #[test]
+    fn no_repeat_batch_has_no_adjacent_duplicates() {
+        let mut selector = NoRepeatSelector::new(two_word_pool(1));
+        let first = selector.new_words(50).unwrap();
+        let second = selector.new_words(50).unwrap();
+
+        assert_eq!(first.len(), 50);
+        assert!(!has_adjacent_repeats(&first));
+        // 两批单词之间也不会重复
+        assert!(!has_adjacent_repeats(&[first, second].concat()));
+    }
+
+    #[test]
+    fn raw_batch_from_two_word_pool_repeats() {
+        // 确认上面的测试确实需要 NoRepeatSelector
+        let words = two_word_pool(1).new_words(50).unwrap();
+        assert!(has_adjacent_repeats(&words));
+    }
+
+    #[test]
+    fn tabs_only_separate_words() {
+        let mut selector = FileTextSelector::new("foo\tbar\t\tbaz\n\tqux").unwrap();
+        let words = selector.new_words(4).unwrap();
+        assert_eq!(words, ["foo", "bar", "baz", "qux"]);
+
+        // 单词之间用一个空格显示，长度与没有制表符的行相同
+        let lines = layout_words(&words, 80, 10, false);
+        assert_eq!(lines, ["foo bar baz qux"]);
+        assert_eq!(lines[0].length(), 15);
+        assert_eq!(Text::from(lines[0].as_str()).length(), 15);
+    }
+
+    const TEN_WORDS: &str = "alpha beta gamma delta epsilon\nzeta eta theta iota kappa";
+
+    #[test]
+    fn from_str_selects_words_from_the_string() {
+        let pool: Vec<&str> = TEN_WORDS.split_whitespace().collect();
+        let mut selector = RawWordSelector::from_str(TEN_WORDS).unwrap().with_seed(7);
+
+        let words = selector.new_words(5).unwrap();
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|word| pool.contains(&word.as_str())));
+
+        let mut same_seed = RawWordSelector::from_str(TEN_WORDS).unwrap().with_seed(7);
+        assert_eq!(same_seed.new_words(5).unwrap(), words);
+    }
+
+    #[test]
+    fn from_str_rejects_strings_without_usable_words() {
+        assert!(RawWordSelector::from_str("").is_err());
+        assert!(RawWordSelector::from_str("a 123 verylongword").is_err());
+    }
+
+    /// 在临时目录中写入一个测试用的文件，返回它的路径
+    fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("typeing-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_and_reported() {
+        // Latin-1 编码的 `café`
+        let path = temp_file("latin1.txt", b"hello caf\xe9 world\n");
+
+        let (contents, replaced) = read_to_string_lossy(&path).unwrap();
+        assert!(replaced);
+        assert_eq!(contents, "hello caf\u{fffd} world\n");
+
+        let selector = RawWordSelector::from_path(path.clone()).unwrap();
+        assert!(selector.replaced_invalid_utf8());
+        // 含有替换字符的单词不是由字母组成的，不会被选中
+        assert_eq!(selector.words, ["hello", "world"]);
+
+        let selector = FileTextSelector::from_path(path.clone()).unwrap();
+        assert!(selector.replaced_invalid_utf8());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn valid_utf8_is_not_reported() {
+        let path = temp_file("utf8.txt", "hello café world\n".as_bytes());
+
+        let selector = RawWordSelector::from_path(path.clone()).unwrap();
+        assert!(!selector.replaced_invalid_utf8());
+        assert_eq!(selector.words, ["hello", "café", "world"]);
+        std::fs::remove_file(path).unwrap();
     }
 }
@@ -0,0 +1,175 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use termion::color::AnsiValue;
+
+use crate::tui::Text;
+
+/// [`UntypedStyle::DimColor`] 默认使用的灰色
+pub const DIM_GRAY: AnsiValue = AnsiValue(245);
+
+/// 还没有输入的文本的样式
+///
+/// 有些终端不支持模糊样式，或者显示得和正常文本一样，这时可以改用灰色
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UntypedStyle {
+    /// 模糊样式
+    Faint,
+    /// 灰色（或者 --untyped-color 指定的颜色）
+    DimColor,
+    /// 不使用任何样式
+    None,
+}
+
+/// 终端界面使用的颜色
+///
+/// 颜色是 256 色调色板中的编号
+#[derive(Clone, Copy, Debug)]
+pub struct Theme {
+    /// 输入正确的字符
+    pub correct: AnsiValue,
+    /// 输入错误的字符
+    pub incorrect: AnsiValue,
+    /// 还没有输入的文本，为 `None` 时 [`UntypedStyle::DimColor`] 使用 [`DIM_GRAY`]
+    pub untyped: Option<AnsiValue>,
+    /// 还没有输入的文本的样式
+    pub untyped_style: UntypedStyle,
+    /// 光标，为 `None` 时使用终端的默认颜色
+    pub cursor: Option<AnsiValue>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        ThemePreset::Default.theme()
+    }
+}
+
+impl Theme {
+    /// 用输入正确的颜色显示文本
+    pub fn correct(&self, text: Text) -> Text {
+        text.with_color(self.correct)
+    }
+
+    /// 用输入错误的颜色显示文本
+    pub fn incorrect(&self, text: Text) -> Text {
+        text.with_color(self.incorrect)
+    }
+
+    /// 用还没有输入的颜色显示文本
+    pub fn untyped(&self, text: Text) -> Text {
+        match self.untyped_style {
+            UntypedStyle::Faint => text.with_faint(),
+            UntypedStyle::DimColor => text.with_color(self.untyped.unwrap_or(DIM_GRAY)),
+            UntypedStyle::None => text,
+        }
+    }
+}
+
+/// 内置的颜色主题
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemePreset {
+    /// 绿色表示正确，红色表示错误，还没有输入的文本为模糊样式
+    Default,
+    /// 蓝色表示正确，黄色表示错误，还没有输入的文本为灰色。适合色盲用户和浅色终端
+    HighContrast,
+}
+
+impl ThemePreset {
+    pub fn theme(&self) -> Theme {
+        match self {
+            Self::Default => Theme {
+                correct: AnsiValue(2),
+                incorrect: AnsiValue(1),
+                untyped: None,
+                untyped_style: UntypedStyle::Faint,
+                cursor: None,
+            },
+            Self::HighContrast => Theme {
+                correct: AnsiValue(12),
+                incorrect: AnsiValue(11),
+                untyped: Some(DIM_GRAY),
+                untyped_style: UntypedStyle::DimColor,
+                cursor: Some(AnsiValue(15)),
+            },
+        }
+    }
+}
+
+/// 256 色调色板中的颜色对应的 RGB 值，用于设置光标的颜色
+pub(crate) fn ansi_to_rgb(color: AnsiValue) -> (u8, u8, u8) {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    // 6x6x6 的颜色立方体中每个分量的取值
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    match color.0 {
+        index @ 0..=15 => BASIC[index as usize],
+        index @ 16..=231 => {
+            let index = index - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        index => {
+            let gray = 8 + 10 * (index - 232);
+            (gray, gray, gray)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use termion::{color, style};
+
+    use super::*;
+
+    fn untyped(untyped_style: UntypedStyle, untyped: Option<AnsiValue>) -> String {
+        let theme = Theme {
+            untyped_style,
+            untyped,
+            ..Theme::default()
+        };
+        theme.untyped(Text::from("word")).to_string()
+    }
+
+    #[test]
+    fn faint_style_uses_the_faint_escape() {
+        let text = untyped(UntypedStyle::Faint, None);
+        assert!(text.contains(&style::Faint.to_string()));
+        assert!(!text.contains(&color::Fg(DIM_GRAY).to_string()));
+    }
+
+    #[test]
+    fn dim_color_style_uses_gray_or_the_chosen_color() {
+        let text = untyped(UntypedStyle::DimColor, None);
+        assert!(text.contains(&color::Fg(DIM_GRAY).to_string()));
+        assert!(!text.contains(&style::Faint.to_string()));
+
+        let text = untyped(UntypedStyle::DimColor, Some(AnsiValue(8)));
+        assert!(text.contains(&color::Fg(AnsiValue(8)).to_string()));
+    }
+
+    #[test]
+    fn none_style_has_no_escapes() {
+        assert_eq!(untyped(UntypedStyle::None, Some(AnsiValue(8))), "word");
+    }
+}
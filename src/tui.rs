@@ -1,24 +1,183 @@
 use std::{
     fmt::Display,
-    io::{stdout, Stdout, Write},
+    io::{self, stdout, Stdout, Write},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        OnceLock,
+    },
+    thread,
+    time::Duration,
 };
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM, SIGWINCH};
+use signal_hook::iterator::{Handle, Signals};
 use termion::{
     clear,
     color::{self, Color},
-    cursor::{self, DetectCursorPos},
+    cursor,
+    event::{Event, Key},
+    get_tty,
+    input::TermRead,
     raw::{IntoRawMode, RawTerminal},
     style, terminal_size,
 };
 
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::theme::{ansi_to_rgb, Theme};
 use crate::TypeingError;
 
 const MIN_LINE_WIDTH: usize = 50;
+/// 默认每行单词最多占终端宽度的百分比
+pub const DEFAULT_LINE_WIDTH_PCT: u16 = 40;
+/// 默认每行最多的单词数
+pub const DEFAULT_MAX_WORDS_PER_LINE: usize = 10;
+/// 终端放不下所有行时，滚动显示的行数
+const SCROLL_WINDOW_LINES: usize = 3;
+/// 开始报告鼠标点击，使用 SGR 格式以支持很宽的终端
+const ENABLE_MOUSE: &str = "\x1b[?1000h\x1b[?1006h";
+/// 停止报告鼠标点击
+const DISABLE_MOUSE: &str = "\x1b[?1006l\x1b[?1000l";
+/// 平滑移动光标时分成的步数
+const SMOOTH_CARET_STEPS: u16 = 4;
+/// 平滑移动光标时每一步之间的时间
+const SMOOTH_CARET_STEP_DELAY: Duration = Duration::from_millis(2);
+
+/// 一个字符在终端中占的列数，控制字符占零列
+fn char_width(c: char) -> usize {
+    UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+/// 一个字符串在终端中占的列数
+///
+/// 按照 Unicode 的东亚宽度计算：中日韩文字和表情符号占两列，组合用字符占零列，其他字符占一列
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// 把 `text` 分成两部分，第一部分最多占 `width` 列
+///
+/// 第一部分至少包含一个字符，所以 `width` 比第一个字符还窄时也能继续分割
+fn split_at_width(text: &str, width: usize) -> (&str, &str) {
+    let mut used = 0;
+    for (i, c) in text.char_indices() {
+        used += char_width(c);
+        if used > width && i > 0 {
+            return text.split_at(i);
+        }
+    }
+
+    (text, "")
+}
+
+/// 把单词分成多行，不会写入终端
+///
+/// 每行最多 `max_per_line` 个单词，并且不超过 `max_width` 列。
+/// 除了最后一行，每行的末尾都有一个空格，因为用户会本能地在每个单词后面键入一个空格。
+/// 空行总是可以放下一个单词，即使它比最大宽度更长；`break_long_words` 为 `true` 时
+/// 这样的单词会在 `max_width` 列处断开，断开的部分单独占一行，末尾没有空格，
+/// 所以显示的字符仍然与需要输入的字符一一对应
+pub fn layout_words(
+    words: &[String],
+    max_width: u16,
+    max_per_line: usize,
+    break_long_words: bool,
+) -> Vec<String> {
+    let max_width = max_width as usize;
+    // 当前行的单词长度
+    let mut current_len = 0;
+    let mut line: Vec<&str> = Vec::new();
+    let mut lines = Vec::new();
+
+    for word in words {
+        let mut word = word.as_str();
+        if break_long_words {
+            while display_width(word) > max_width {
+                let (head, tail) = split_at_width(word, max_width);
+                if !line.is_empty() {
+                    lines.push(line.join(" ") + " ");
+                    line.clear();
+                    current_len = 0;
+                }
+                lines.push(head.to_string());
+                word = tail;
+            }
+        }
+
+        let word_len = display_width(word);
+        let new_len = current_len + word_len + 1;
+        // 行字长小于最大宽度，并且下一次增加的单词不超过最大宽度。那么才追加单词到当前行
+        if line.is_empty() || (line.len() < max_per_line && new_len <= max_width) {
+            line.push(word);
+            current_len = new_len;
+        } else {
+            lines.push(line.join(" ") + " ");
+            // 新行的第一个单词
+            line = vec![word];
+            current_len = word_len + 1;
+        }
+    }
+    lines.push(line.join(" "));
+
+    lines
+}
+
+/// 在 `height` 行高的终端中垂直居中显示 `num_lines` 行时第一行的行号（从 1 开始）
+///
+/// 行数的奇偶不影响居中，不能均分时上方的空行少一行。放不下所有行时从第一行开始显示
+///
+/// ```
+/// use typeing::tui::centered_top_row;
+///
+/// assert_eq!(centered_top_row(1, 5), 3);
+/// assert_eq!(centered_top_row(2, 5), 2);
+/// assert_eq!(centered_top_row(5, 5), 1);
+/// assert_eq!(centered_top_row(5, 3), 1);
+/// ```
+pub fn centered_top_row(num_lines: usize, height: u16) -> u16 {
+    let num_lines = u16::try_from(num_lines).unwrap_or(u16::MAX);
+    height.saturating_sub(num_lines) / 2 + 1
+}
+
+/// 在 `height` 行高的终端底部显示 `num_lines` 行时第 `line_no` 行（从 0 开始）的行号（从 1 开始）
+///
+/// 最后一行下面空出一行。终端放不下所有行时，上面放不下的行都显示在第一行，不会计算出 0 或者溢出
+pub fn bottom_row(line_no: usize, num_lines: usize, height: u16) -> u16 {
+    let line_no = u16::try_from(line_no).unwrap_or(u16::MAX);
+    let num_lines = u16::try_from(num_lines).unwrap_or(u16::MAX);
+    height
+        .saturating_sub(1)
+        .saturating_add(line_no)
+        .saturating_sub(num_lines)
+        .max(1)
+}
 
 /// 描述具有可打印长度的内容
 ///
 /// 例如，包含颜色字符的字符串在打印时的长度与其中的字节数或字符数不同
+///
+/// 长度是打印时在终端中占的列数（参见 [`display_width`]），而不是字节数
 pub trait HasLength {
     fn length(&self) -> usize;
+
+    /// 每个字符开始的列，从第一个字符开始的列 0 算起
+    ///
+    /// 中日韩文字之后的字符向右移动两列，组合用字符与下一个字符在同一列
+    fn char_columns(&self) -> Vec<usize>;
+}
+
+/// `text` 中每个字符开始的列，参见 [`HasLength::char_columns`]
+fn char_columns(text: &str) -> Vec<usize> {
+    let mut column = 0;
+    text.chars()
+        .map(|c| {
+            let start = column;
+            column += char_width(c);
+            start
+        })
+        .collect()
 }
 
 /// 保存要在终端上打印的一些文本
@@ -45,9 +204,11 @@ pub struct Text {
 
 impl Text {
     /// 从原始字符串构造一个新的Text
-    /// 提示：确保此字符串本身没有格式化字符、零宽度字符或多宽度字符
+    /// 提示：确保此字符串本身没有格式化字符
     pub fn new(text: String) -> Self {
-        let length = text.len();
+        // 按终端列数计数，非 ASCII 字符（例如 "é"）占多个字节但只打印一列，
+        // 而中日韩文字占两列
+        let length = display_width(&text);
         Self {
             raw_text: text.clone(),
             text,
@@ -77,6 +238,12 @@ impl Text {
         self
     }
 
+    /// 反转文本的前景色和背景色
+    pub fn with_reverse(mut self) -> Self {
+        self.raw_text = format!("{}{}{}", style::Invert, self.raw_text, style::NoInvert);
+        self
+    }
+
     /// 为文本添加指定的颜色
     pub fn with_color<C>(mut self, color: C) -> Self
     where
@@ -96,12 +263,47 @@ impl HasLength for Text {
     fn length(&self) -> usize {
         self.length
     }
+
+    fn char_columns(&self) -> Vec<usize> {
+        char_columns(&self.text)
+    }
 }
 
 impl HasLength for [Text] {
     fn length(&self) -> usize {
         self.iter().map(|t| t.length()).sum()
     }
+
+    fn char_columns(&self) -> Vec<usize> {
+        let mut start = 0;
+        let mut columns = Vec::new();
+        for text in self {
+            columns.extend(text.char_columns().into_iter().map(|c| start + c));
+            start += text.length();
+        }
+        columns
+    }
+}
+
+/// 与 [`Text`] 相同，按照 [`display_width`] 计算，不需要先构造一个 [`Text`]
+impl HasLength for str {
+    fn length(&self) -> usize {
+        display_width(self)
+    }
+
+    fn char_columns(&self) -> Vec<usize> {
+        char_columns(self)
+    }
+}
+
+impl HasLength for String {
+    fn length(&self) -> usize {
+        self.as_str().length()
+    }
+
+    fn char_columns(&self) -> Vec<usize> {
+        self.as_str().char_columns()
+    }
 }
 
 impl From<String> for Text {
@@ -128,15 +330,79 @@ impl Display for Text {
     }
 }
 
+/// 测试中光标的形状
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CursorStyle {
+    /// 竖线
+    Bar,
+    /// 闪烁的竖线
+    BlinkingBar,
+    /// 方块
+    Block,
+    /// 闪烁的方块
+    BlinkingBlock,
+    /// 下划线
+    Underline,
+    /// 闪烁的下划线
+    BlinkingUnderline,
+}
+
+impl Display for CursorStyle {
+    /// 设置光标形状的转义序列
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bar => write!(f, "{}", cursor::SteadyBar),
+            Self::BlinkingBar => write!(f, "{}", cursor::BlinkingBar),
+            Self::Block => write!(f, "{}", cursor::SteadyBlock),
+            Self::BlinkingBlock => write!(f, "{}", cursor::BlinkingBlock),
+            Self::Underline => write!(f, "{}", cursor::SteadyUnderline),
+            Self::BlinkingUnderline => write!(f, "{}", cursor::BlinkingUnderline),
+        }
+    }
+}
+
 /// 一行字的位置
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 struct LinePos {
     /// 终端窗口中该行的 y 位置
     pub y: u16,
     /// 行中第一个字符的 x 位置
     pub x: u16,
-    /// 该行的长度（字符数）
-    pub length: u16,
+    /// 行中每个字符相对于 `x` 的列，长度就是这一行的字符数
+    pub columns: Vec<u16>,
+    /// 该行在终端中占的列数
+    pub width: u16,
+}
+
+impl LinePos {
+    fn new<T>(x: u16, y: u16, line: &T) -> Self
+    where
+        T: HasLength + ?Sized,
+    {
+        Self {
+            y,
+            x,
+            columns: line.char_columns().into_iter().map(|c| c as u16).collect(),
+            width: line.length() as u16,
+        }
+    }
+
+    /// 该行的字符数
+    fn length(&self) -> u16 {
+        self.columns.len() as u16
+    }
+
+    /// 第 `index` 个字符占的列数，组合用字符占零列
+    fn char_width(&self, index: u16) -> u16 {
+        let start = self.columns[index as usize];
+        let end = self
+            .columns
+            .get(index as usize + 1)
+            .copied()
+            .unwrap_or(self.width);
+        end - start
+    }
 }
 
 /// 光标位置
@@ -144,6 +410,10 @@ struct CursorPos {
     pub lines: Vec<LinePos>,
     pub cur_line: usize,
     pub cur_char_in_line: u16,
+    /// 插入到行中的多余字符，每一项是 (行, 插入的位置, 占用的列数)
+    ///
+    /// 插入位置和它之后的字符都会向右移动
+    pub insertions: Vec<(usize, u16, u16)>,
 }
 
 impl CursorPos {
@@ -152,12 +422,46 @@ impl CursorPos {
             lines: Vec::new(),
             cur_line: 0,
             cur_char_in_line: 0,
+            insertions: Vec::new(),
+        }
+    }
+
+    /// 当前行中在光标位置之前（包括光标位置）插入的列数
+    fn shift(&self) -> u16 {
+        self.shift_at(self.cur_line, self.cur_char_in_line)
+    }
+
+    /// 第 `line` 行中在 `index` 位置之前（包括这个位置）插入的列数
+    fn shift_at(&self, line: usize, index: u16) -> u16 {
+        self.insertions
+            .iter()
+            .filter(|(l, i, _)| *l == line && *i <= index)
+            .map(|(_, _, width)| width)
+            .sum()
+    }
+
+    /// 设置在光标位置插入的列数，`width` 为 0 时删除插入
+    fn set_insertion(&mut self, width: u16) {
+        let (line, index) = (self.cur_line, self.cur_char_in_line);
+        self.insertions.retain(|&(l, i, _)| (l, i) != (line, index));
+        if width > 0 {
+            self.insertions.push((line, index, width));
         }
     }
 
+    /// 光标所在的行中，从光标位置到行尾的字符数
+    fn remaining_in_line(&self) -> usize {
+        self.lines
+            .get(self.cur_line)
+            .map_or(0, |line| (line.length() - self.cur_char_in_line) as usize)
+    }
+
     pub fn next(&mut self) -> (u16, u16) {
-        let line = self.lines[self.cur_line];
-        let max_chars_index = line.length - 1;
+        if self.lines.is_empty() {
+            return self.cur_pos();
+        }
+
+        let max_chars_index = self.lines[self.cur_line].length().saturating_sub(1);
 
         if self.cur_char_in_line < max_chars_index {
             // 如果未超过最大字符，则当前字符位置+1
@@ -174,6 +478,10 @@ impl CursorPos {
     }
 
     pub fn prev(&mut self) -> (u16, u16) {
+        if self.lines.is_empty() {
+            return self.cur_pos();
+        }
+
         if self.cur_char_in_line > 0 {
             // 当前行可以向前移动字符
             self.cur_char_in_line -= 1;
@@ -182,16 +490,217 @@ impl CursorPos {
             if self.cur_line > 0 {
                 // 并且不是在第一行，则代表可以继续向上移动行
                 self.cur_line -= 1;
-                self.cur_char_in_line = self.lines[self.cur_line].length - 1;
+                self.cur_char_in_line = self.lines[self.cur_line].length().saturating_sub(1);
             }
         }
 
         self.cur_pos()
     }
 
+    /// 光标在终端窗口中的位置
+    ///
+    /// 没有任何行的时候返回终端左上角 `(1, 1)`
     pub fn cur_pos(&self) -> (u16, u16) {
-        let line = self.lines[self.cur_line];
-        (line.x + self.cur_char_in_line, line.y)
+        match self.lines.get(self.cur_line) {
+            Some(line) => {
+                let column = line
+                    .columns
+                    .get(self.cur_char_in_line as usize)
+                    .copied()
+                    .unwrap_or(line.width);
+                (line.x + column + self.shift(), line.y)
+            }
+            None => (1, 1),
+        }
+    }
+}
+
+/// 方块光标的位置和显示的文本，参见 [`TypeingTui::display_block_caret`]
+///
+/// 方块总是画在 [`CursorPos::cur_pos`] 的位置上。`highlight` 为 `true` 时当前单词已经反转了颜色，
+/// 方块不再反转，这样仍然可以分辨出来。光标所在的行中没有字符时返回 `None`
+fn block_caret(
+    cursor_pos: &CursorPos,
+    target: char,
+    highlight: bool,
+    theme: &Theme,
+) -> Option<((u16, u16), Text)> {
+    if cursor_pos.remaining_in_line() == 0 {
+        return None;
+    }
+
+    let caret = theme.untyped(Text::from(target));
+    let caret = if highlight {
+        caret
+    } else {
+        caret.with_reverse()
+    };
+
+    Some((cursor_pos.cur_pos(), caret))
+}
+
+/// 后台线程发送给 [`KeyReader`] 的输入
+enum Input {
+    /// 从终端读取的按键或者鼠标事件
+    Event(io::Result<Event>),
+    /// 收到了 SIGWINCH，终端的大小改变了
+    Resize,
+}
+
+/// 在后台线程中从终端读取按键
+///
+/// 这样主循环可以在等待按键的同时检查计时。终端的大小改变时也会唤醒等待按键的主循环，
+/// 主循环不需要定时检查终端的大小
+pub struct KeyReader {
+    events: Receiver<Input>,
+    /// 用来在释放时停止接收 SIGWINCH 的线程
+    resize_signals: Option<Handle>,
+}
+
+impl KeyReader {
+    /// 启动读取按键和接收 SIGWINCH 的后台线程
+    pub fn new() -> Self {
+        let (send, events) = mpsc::channel();
+
+        // 无法接收 SIGWINCH 时只是在下一次按键时才会重新排列单词
+        let resize_signals = Signals::new([SIGWINCH]).ok().map(|mut signals| {
+            let handle = signals.handle();
+            let send = send.clone();
+            thread::spawn(move || {
+                for _ in signals.forever() {
+                    if send.send(Input::Resize).is_err() {
+                        return;
+                    }
+                }
+            });
+            handle
+        });
+
+        thread::spawn(move || {
+            let tty = match get_tty() {
+                Ok(tty) => tty,
+                Err(err) => {
+                    let _ = send.send(Input::Event(Err(err)));
+                    return;
+                }
+            };
+
+            for event in tty.events() {
+                if send.send(Input::Event(event)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            events,
+            resize_signals,
+        }
+    }
+
+    /// 等待下一个按键，忽略鼠标事件和终端大小的改变
+    pub fn next_key(&self) -> MaybeError<Key> {
+        loop {
+            if let Some(Event::Key(key)) = self.next_event()? {
+                return Ok(key);
+            }
+        }
+    }
+
+    /// 等待下一个按键或者鼠标事件
+    ///
+    /// 终端的大小改变时返回 `None`，调用者应该检查 [`TypeingTui::resized`]
+    pub fn next_event(&self) -> MaybeError<Option<Event>> {
+        match self.events.recv() {
+            Ok(Input::Event(event)) => Ok(Some(event?)),
+            Ok(Input::Resize) => Ok(None),
+            Err(_) => Err(TypeingError::from("终端输入已关闭".to_string())),
+        }
+    }
+
+    /// 最多等待 `timeout` 时间的下一个按键
+    ///
+    /// 超时、终端的大小改变或者收到了鼠标事件时返回 `None`
+    pub fn next_key_timeout(&self, timeout: Duration) -> MaybeError<Option<Key>> {
+        match self.next_event_timeout(timeout)? {
+            Some(Event::Key(key)) => Ok(Some(key)),
+            _ => Ok(None),
+        }
+    }
+
+    /// 最多等待 `timeout` 时间的下一个按键或者鼠标事件
+    ///
+    /// 超时或者终端的大小改变时返回 `None`，参见 [`TypeingTui::with_mouse`]
+    pub fn next_event_timeout(&self, timeout: Duration) -> MaybeError<Option<Event>> {
+        match self.events.recv_timeout(timeout) {
+            Ok(Input::Event(event)) => Ok(Some(event?)),
+            Ok(Input::Resize) | Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(TypeingError::from("终端输入已关闭".to_string()))
+            }
+        }
+    }
+}
+
+impl Default for KeyReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for KeyReader {
+    fn drop(&mut self) {
+        if let Some(handle) = &self.resize_signals {
+            handle.close();
+        }
+    }
+}
+
+/// 进入原始模式之前终端的设置，收到信号退出时用来恢复终端
+static ORIGINAL_TERMIOS: OnceLock<libc::termios> = OnceLock::new();
+
+/// 收到信号退出时写入终端的内容：停止报告鼠标点击、恢复光标的颜色和形状、清空屏幕并显示光标
+///
+/// 与 [`TypeingTui`] 的 `Drop` 相同。接收信号的线程不能使用主线程中的 [`TypeingTui`]
+const SIGNAL_RESET: &[u8] = b"\x1b[?1006l\x1b[?1000l\x1b]112\x07\x1b[2J\x1b[2 q\x1b[H\x1b[?25h";
+
+/// 在收到 SIGINT、SIGTERM 或者 SIGHUP 时恢复终端然后退出
+///
+/// 原始模式下 ctrl-c 不会产生 SIGINT，但是用 `kill` 结束进程时不会运行 `Drop`，
+/// 不恢复的话用户的 shell 会一直停留在原始模式。必须在进入原始模式之前调用，
+/// 多次调用时只有第一次有效
+fn install_signal_handlers() {
+    // SAFETY: termios 是普通的 C 结构体，全部为 0 是有效的值，tcgetattr 会覆盖它
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    // SAFETY: 传入的指针指向有效的 termios
+    if unsafe { libc::tcgetattr(libc::STDOUT_FILENO, &mut termios) } != 0 {
+        return;
+    }
+    if ORIGINAL_TERMIOS.set(termios).is_err() {
+        return;
+    }
+
+    let Ok(mut signals) = Signals::new([SIGINT, SIGTERM, SIGHUP]) else {
+        return;
+    };
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            restore_terminal();
+            std::process::exit(128 + signal);
+        }
+    });
+}
+
+/// 把终端恢复到进入原始模式之前的状态，参见 [`install_signal_handlers`]
+fn restore_terminal() {
+    let mut stdout = stdout();
+    let _ = stdout.write_all(SIGNAL_RESET);
+    let _ = stdout.flush();
+    if let Some(termios) = ORIGINAL_TERMIOS.get() {
+        // SAFETY: 传入的指针指向有效的 termios
+        unsafe {
+            libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSANOW, termios);
+        }
     }
 }
 
@@ -201,19 +710,156 @@ pub struct TypeingTui {
     cursor_pos: CursorPos,
     track_lines: bool,
     bottom_lines_len: usize,
+    /// 上一次重置屏幕时终端的大小
+    size: (u16, u16),
+    /// 每行单词最多占终端宽度的百分比
+    line_width_pct: u16,
+    /// 每行最多的单词数
+    max_words_per_line: usize,
+    /// 每行固定的单词数，设置时忽略终端宽度，参见 [`TypeingTui::with_words_per_line`]
+    words_per_line: Option<usize>,
+    /// 是否把比一行更长的单词分到多行显示
+    break_long_words: bool,
+    /// 终端放不下所有行，只显示其中的 [`SCROLL_WINDOW_LINES`] 行
+    scrolling: bool,
+    /// 滚动显示时显示的第一行
+    first_visible_line: usize,
+    /// 还没有输入的文本和光标的颜色
+    theme: Theme,
+    /// 测试中光标的形状
+    cursor_style: CursorStyle,
+    /// 光标一次移动多个字符时是否平滑地移动
+    smooth_caret: bool,
+    /// 最后一行的末尾是否也有一个空格
+    final_space: bool,
+    /// 是否报告鼠标点击
+    mouse: bool,
+    /// 是否从终端的左上角开始显示，而不是居中显示
+    minimal: bool,
+    /// 是否暂时不刷新输出，参见 [`TypeingTui::begin_batch`]
+    batching: bool,
+    /// 是否用反转的方块代替终端的光标，参见 [`TypeingTui::with_block_caret`]
+    block_caret: bool,
 }
 
 type MaybeError<T = ()> = Result<T, TypeingError>;
 
 impl TypeingTui {
     /// 为TUI初始化原始模式的标准输出
-    pub fn new() -> Self {
-        Self {
-            stdout: stdout().into_raw_mode().unwrap(),
+    ///
+    /// 同时安装信号处理函数，进程被信号结束时也会恢复终端
+    ///
+    /// 标准输出不是终端或者无法进入原始模式时返回错误
+    pub fn new() -> MaybeError<Self> {
+        let stdout = stdout();
+        if !termion::is_tty(&stdout) {
+            return Err(TypeingError::from(
+                "typeing 需要在交互式终端中运行，标准输出不能重定向到文件或管道".to_string(),
+            ));
+        }
+        // 必须在进入原始模式之前保存终端的设置，否则收到信号时恢复的是原始模式
+        install_signal_handlers();
+        let stdout = stdout.into_raw_mode()?;
+        Ok(Self {
+            stdout,
             cursor_pos: CursorPos::new(),
             track_lines: false,
             bottom_lines_len: 0,
-        }
+            size: (0, 0),
+            line_width_pct: DEFAULT_LINE_WIDTH_PCT,
+            max_words_per_line: DEFAULT_MAX_WORDS_PER_LINE,
+            words_per_line: None,
+            break_long_words: false,
+            scrolling: false,
+            first_visible_line: 0,
+            theme: Theme::default(),
+            cursor_style: CursorStyle::BlinkingBar,
+            smooth_caret: false,
+            final_space: false,
+            mouse: false,
+            minimal: false,
+            batching: false,
+            block_caret: false,
+        })
+    }
+
+    /// 设置 [`TypeingTui::display_words`] 中每行的宽度和单词数
+    ///
+    /// `line_width_pct` 是每行最多占终端宽度的百分比
+    pub fn with_line_layout(mut self, line_width_pct: u16, max_words_per_line: usize) -> Self {
+        self.line_width_pct = line_width_pct;
+        self.max_words_per_line = max_words_per_line;
+        self
+    }
+
+    /// 每行固定显示 `words_per_line` 个单词（最后一行可以更少），不再根据终端宽度决定
+    ///
+    /// 这样同一组单词在不同大小的终端中的分行是一样的。有一行比终端更宽时
+    /// [`TypeingTui::display_words`] 返回错误
+    pub fn with_words_per_line(mut self, words_per_line: Option<usize>) -> Self {
+        self.words_per_line = words_per_line;
+        self
+    }
+
+    /// 在光标所在的字符上显示反转颜色的方块，并一直隐藏终端的光标
+    ///
+    /// 适合终端的光标不容易看清的情况。方块由 [`TypeingTui::display_block_caret`] 显示
+    pub fn with_block_caret(mut self, block_caret: bool) -> Self {
+        self.block_caret = block_caret;
+        self
+    }
+
+    /// 比一行更长的单词在行尾断开，剩下的部分显示在下一行
+    ///
+    /// 不设置时这样的单词单独占一行，比终端还宽时 [`TypeingTui::display_words`] 返回错误
+    pub fn with_break_long_words(mut self, break_long_words: bool) -> Self {
+        self.break_long_words = break_long_words;
+        self
+    }
+
+    /// 测试中使用 `cursor_style` 形状的光标，退出时总是恢复为不闪烁的方块
+    pub fn with_cursor_style(mut self, cursor_style: CursorStyle) -> Self {
+        self.cursor_style = cursor_style;
+        self
+    }
+
+    /// 从终端的左上角开始显示每一行，而不是在终端中间居中显示
+    ///
+    /// 显示的位置不依赖终端的大小，适合很小的终端或者用脚本读取输出
+    pub fn with_minimal(mut self, minimal: bool) -> Self {
+        self.minimal = minimal;
+        self
+    }
+
+    /// 让终端报告鼠标点击，参见 [`KeyReader::next_event_timeout`] 和 [`TypeingTui::text_index_at`]
+    ///
+    /// 启用之后终端通常不能再用鼠标选择文本，所以默认不启用
+    pub fn with_mouse(mut self, mouse: bool) -> Self {
+        self.mouse = mouse;
+        self
+    }
+
+    /// 在最后一行的末尾也显示一个空格，参见 `--require-final-space`
+    ///
+    /// 默认最后一行的末尾没有空格，输入最后一个字符时测试就结束了
+    pub fn with_final_space(mut self, final_space: bool) -> Self {
+        self.final_space = final_space;
+        self
+    }
+
+    /// 光标在同一行中一次移动多个字符时（例如 ctrl-w），经过中间的位置平滑地移动
+    ///
+    /// `$TERM` 为 `dumb` 的终端不支持移动光标，总是不会启用
+    pub fn with_smooth_caret(mut self, smooth_caret: bool) -> Self {
+        let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+        self.smooth_caret = smooth_caret && !dumb;
+        self
+    }
+
+    /// 使用 `theme` 中的颜色显示还没有输入的文本和光标
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
     }
 
     // 重置光标
@@ -223,25 +869,59 @@ impl TypeingTui {
 
     // 刷新终端
     pub fn flush(&mut self) -> MaybeError {
+        if self.batching {
+            return Ok(());
+        }
+
         self.stdout.flush()?;
         Ok(())
     }
 
+    /// 在 [`TypeingTui::end_batch`] 之前不刷新输出
+    ///
+    /// 清空屏幕之后重新显示所有内容时使用，这样终端不会显示中间的空白屏幕
+    pub fn begin_batch(&mut self) {
+        self.batching = true;
+    }
+
+    /// 恢复刷新输出，并刷新 [`TypeingTui::begin_batch`] 之后的所有内容
+    pub fn end_batch(&mut self) -> MaybeError {
+        self.batching = false;
+        self.flush()
+    }
+
     /// 重置Tui
     pub fn reset_screen(&mut self) -> MaybeError {
         let (sizex, sizey) = terminal_size()?;
+        self.size = (sizex, sizey);
 
         write!(
             self.stdout,
             "{}{}{}",
             clear::All,
             cursor::Goto(sizex / 2, sizey / 2),
-            cursor::BlinkingBar
+            self.cursor_style
         )?;
+        if self.mouse {
+            write!(self.stdout, "{}", ENABLE_MOUSE)?;
+        }
+        if self.block_caret {
+            write!(self.stdout, "{}", cursor::Hide)?;
+        }
+        if let Some(color) = self.theme.cursor {
+            // OSC 12 设置光标的颜色
+            let (r, g, b) = ansi_to_rgb(color);
+            write!(self.stdout, "\x1b]12;#{:02x}{:02x}{:02x}\x07", r, g, b)?;
+        }
         self.flush()?;
         Ok(())
     }
 
+    /// 终端的大小在上一次重置屏幕之后是否改变了
+    pub fn resized(&self) -> MaybeError<bool> {
+        Ok(terminal_size()? != self.size)
+    }
+
     /// 显示单行文本
     pub fn display_a_line(&mut self, text: &[Text]) -> MaybeError {
         self.display_a_line_raw(text)?;
@@ -257,16 +937,11 @@ impl TypeingTui {
         [T]: HasLength,
         T: Display,
     {
-        let len = text.as_ref().len() as u16;
+        let len = text.as_ref().length() as u16;
         write!(self.stdout, "{}", cursor::Left(len / 2))?;
 
-        if self.track_lines {
-            let (x, y) = self.stdout.cursor_pos()?;
-            self.cursor_pos.lines.push(LinePos { x, y, length: len })
-        }
-
         for t in text.as_ref() {
-            self.display_raw_text(t)?;
+            write!(self.stdout, "{}", t)?;
         }
 
         write!(self.stdout, "{}", cursor::Left(len))?;
@@ -277,22 +952,52 @@ impl TypeingTui {
     /// 显示多行文本
     ///
     /// - 一行文本由一段 [`Text`] 描述，它们连接并显示在同一行上
-    /// 这些线垂直居中，每条线本身水平居中
+    /// - 这些线垂直居中，每条线本身水平居中
+    /// - 使用 [`TypeingTui::with_minimal`] 时从终端的左上角开始显示，不居中
     pub fn display_lines<T, U>(&mut self, lines: &[T]) -> MaybeError
     where
         T: AsRef<[U]>,
         [U]: HasLength,
         U: Display,
     {
+        if self.minimal {
+            for (line_no, line) in lines.iter().enumerate() {
+                let y = line_no as u16 + 1;
+                write!(self.stdout, "{}", cursor::Goto(1, y))?;
+                if self.track_lines {
+                    self.cursor_pos
+                        .lines
+                        .push(LinePos::new(1, y, line.as_ref()))
+                }
+                for text in line.as_ref() {
+                    write!(self.stdout, "{}", text)?;
+                }
+            }
+            self.flush()?;
+
+            return Ok(());
+        }
+
         let (sizex, sizey) = terminal_size()?;
-        let line_offset = lines.len() as u16 / 2;
+        let top = centered_top_row(lines.len(), sizey);
 
         for (line_no, line) in lines.iter().enumerate() {
-            write!(
-                self.stdout,
-                "{}",
-                cursor::Goto(sizex / 2, sizey / 2 + (line_no as u16) - line_offset)
-            )?;
+            let y = top.saturating_add(line_no as u16);
+
+            if self.track_lines {
+                // 与 `display_a_line_raw` 中向左移动的距离一致，光标不会移出第一列
+                let length = line.as_ref().length() as u16;
+                let x = std::cmp::max((sizex / 2).saturating_sub(length / 2), 1);
+                self.cursor_pos
+                    .lines
+                    .push(LinePos::new(x, y, line.as_ref()))
+            }
+
+            // 终端会把超出底部的位置当作最后一行，放不下的行不显示，以免覆盖其他行
+            if y > sizey {
+                continue;
+            }
+            write!(self.stdout, "{}", cursor::Goto(sizex / 2, y))?;
             self.display_a_line_raw(line.as_ref())?;
         }
         self.flush()?;
@@ -308,15 +1013,18 @@ impl TypeingTui {
         U: Display,
     {
         let (sizex, sizey) = terminal_size()?;
-        let line_offset = lines.len() as u16;
 
         self.bottom_lines_len = lines.len();
 
         for (line_no, line) in lines.iter().enumerate() {
+            let y = bottom_row(line_no, lines.len(), sizey);
+            // 底部的行会被反复重绘，先清除上一次留下的文本
             write!(
                 self.stdout,
-                "{}",
-                cursor::Goto(sizex / 2, sizey - 1 + (line_no as u16) - line_offset)
+                "{}{}{}",
+                cursor::Goto(1, y),
+                clear::CurrentLine,
+                cursor::Goto(sizex / 2, y)
             )?;
             self.display_a_line_raw(line.as_ref())?;
         }
@@ -327,49 +1035,71 @@ impl TypeingTui {
 
     pub fn display_words(&mut self, words: &[String]) -> MaybeError<Vec<Text>> {
         self.reset();
-        // 当前行的单词长度
-        let mut current_len = 0;
-        let mut max_word_len = 0;
-        let mut line = Vec::new();
-        let mut lines = Vec::new();
         let (terminal_width, terminal_height) = terminal_size()?;
-        // 控制台40%宽
-        let max_width = terminal_width * 2 / 5;
-        const MAX_WORDS_PER_LINE: usize = 10;
-
-        for word in words {
-            // +1 是因为行尾有一个额外的空格
-            max_word_len = std::cmp::max(max_word_len, word.len() + 1);
-            let new_len = current_len + word.len() as u16 + 1;
-            // 行字长小于总宽40%，并且下一次增加的单词不超过总宽40%。那么才追加单词到当前行
-            if line.len() < MAX_WORDS_PER_LINE && new_len <= max_width {
-                line.push(word.clone());
-                current_len += word.len() as u16 + 1
-            } else {
-                // 在每行的末尾添加一个额外的空格，因为用户会本能地在每个单词后面键入一个空格(至少我是这样做的)
-                // 追加一行
-                lines.push(Text::from(line.join(" ") + " ").with_faint());
-
-                // 新行的第一个单词
-                line = vec![word.clone()];
-                current_len = word.len() as u16 + 1;
+        // 默认为控制台40%宽
+        let max_width = (terminal_width as u32 * self.line_width_pct as u32 / 100) as u16;
+
+        let mut lines = match self.words_per_line {
+            // 每行的单词数固定时不限制宽度，下面检查是否放得下
+            Some(words_per_line) => layout_words(words, u16::MAX, words_per_line, false),
+            None => layout_words(
+                words,
+                max_width,
+                self.max_words_per_line,
+                self.break_long_words,
+            ),
+        };
+        if self.final_space {
+            if let Some(last_line) = lines.last_mut() {
+                last_line.push(' ');
             }
         }
-
-        lines.push(Text::from(line.join(" ")).with_faint());
-        max_word_len = std::cmp::max(max_word_len + 1, MIN_LINE_WIDTH);
-        if lines.len() + self.bottom_lines_len + 2 > terminal_height as usize {
+        let lines: Vec<Text> = lines
+            .into_iter()
+            .map(|line| self.theme.untyped(Text::from(line)))
+            .collect();
+
+        // 最长的单词，用于在终端太窄时提示是哪个单词
+        let longest_word = words
+            .iter()
+            .max_by_key(|word| display_width(word))
+            .map_or("", String::as_str);
+        // +1 是因为行尾有一个额外的空格
+        let mut max_word_len = display_width(longest_word) + 1;
+        if self.break_long_words {
+            // 断开之后每一部分都不会比一行更宽
+            max_word_len = max_word_len.min(max_width as usize + 1);
+        }
+        if !self.break_long_words && max_word_len + 1 > terminal_width as usize {
             return Err(TypeingError::from(format!(
-                "终端高度太短! Typeing 至少需要 {} 行，得到 {} 行",
-                lines.len() + self.bottom_lines_len + 2,
-                terminal_height
+                "单词 `{}` 太长，终端至少需要 {} 列，得到 {} 列。可以使用 --break-long-words 把它分到多行显示",
+                longest_word,
+                max_word_len + 1,
+                terminal_width
             )));
-        } else if max_word_len > terminal_width as usize {
+        }
+        if let Some(words_per_line) = self.words_per_line {
+            let widest = lines.iter().map(|line| line.length).max().unwrap_or(0);
+            if widest > terminal_width as usize {
+                return Err(TypeingError::from(format!(
+                    "每行 {} 个单词时终端至少需要 {} 列，得到 {} 列",
+                    words_per_line, widest, terminal_width
+                )));
+            }
+        }
+        max_word_len = std::cmp::max(max_word_len + 1, MIN_LINE_WIDTH);
+        if max_word_len > terminal_width as usize {
             return Err(TypeingError::from(format!(
                 "终端宽度太低! Typeing 至少需要 {} 列，得到 {} 列",
                 max_word_len, terminal_width
             )));
         }
+        if lines.len() + self.bottom_lines_len + 2 > terminal_height as usize {
+            self.display_words_scrolling(&lines, terminal_height)?;
+            return Ok(lines);
+        }
+
+        self.scrolling = false;
         self.track_lines = true;
         self.display_lines(
             lines
@@ -386,16 +1116,139 @@ impl TypeingTui {
         Ok(lines)
     }
 
+    /// 终端放不下所有行时，只显示从 `first_visible_line` 开始的 [`SCROLL_WINDOW_LINES`] 行
+    ///
+    /// 其他行的位置仍然会被记录，光标可以移动到这些行上，但是在这些行上输入的字符不会显示。
+    /// 光标移动到另一行之后用 [`TypeingTui::scroll_to_cursor`] 检查是否需要重新显示
+    fn display_words_scrolling(&mut self, lines: &[Text], terminal_height: u16) -> MaybeError {
+        if SCROLL_WINDOW_LINES + self.bottom_lines_len + 2 > terminal_height as usize {
+            return Err(TypeingError::from(format!(
+                "终端高度太短! Typeing 至少需要 {} 行，得到 {} 行",
+                SCROLL_WINDOW_LINES + self.bottom_lines_len + 2,
+                terminal_height
+            )));
+        }
+
+        self.scrolling = true;
+        let first = self
+            .first_visible_line
+            .min(lines.len().saturating_sub(SCROLL_WINDOW_LINES));
+        self.first_visible_line = first;
+        let window = &lines[first..first + SCROLL_WINDOW_LINES];
+
+        self.track_lines = true;
+        self.display_lines(
+            window
+                .iter()
+                .cloned()
+                .map(|line| [line])
+                .collect::<Vec<[Text; 1]>>()
+                .as_slice(),
+        )?;
+        self.track_lines = false;
+
+        // 不显示的行放在窗口的第一行或者最后一行的位置上
+        let top = &self.cursor_pos.lines[0];
+        let bottom = &self.cursor_pos.lines[SCROLL_WINDOW_LINES - 1];
+        let hidden = |line: &Text, pos: &LinePos| LinePos::new(pos.x, pos.y, line);
+        let above: Vec<LinePos> = lines[..first]
+            .iter()
+            .map(|line| hidden(line, top))
+            .collect();
+        let below: Vec<LinePos> = lines[first + SCROLL_WINDOW_LINES..]
+            .iter()
+            .map(|line| hidden(line, bottom))
+            .collect();
+        self.cursor_pos.lines.splice(0..0, above);
+        self.cursor_pos.lines.extend(below);
+
+        self.move_to_cur_pos()?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    /// 滚动显示时，让光标所在的行显示在窗口的第二行（第一行和最后一行除外）
+    ///
+    /// 返回显示的行是否改变了，改变之后需要重新显示单词和已经输入的进度
+    pub fn scroll_to_cursor(&mut self) -> bool {
+        if !self.scrolling {
+            return false;
+        }
+
+        let max_first = self
+            .cursor_pos
+            .lines
+            .len()
+            .saturating_sub(SCROLL_WINDOW_LINES);
+        let first = self.current_line().saturating_sub(1).min(max_first);
+        if first == self.first_visible_line {
+            return false;
+        }
+
+        self.first_visible_line = first;
+        true
+    }
+
+    /// 显示新的单词之前回到第一行
+    pub fn scroll_to_top(&mut self) {
+        self.first_visible_line = 0;
+    }
+
+    /// 光标所在的行是否显示在屏幕上
+    fn cursor_line_visible(&self) -> bool {
+        !self.scrolling
+            || (self.first_visible_line..self.first_visible_line + SCROLL_WINDOW_LINES)
+                .contains(&self.current_line())
+    }
+
+    /// 终端中 `(x, y)` 位置上的字符在需要输入的字符中的位置
+    ///
+    /// 这个位置不是单词中的字符（例如多余字符或者空白处），或者滚动显示时这一行不在屏幕上时返回 `None`
+    pub fn text_index_at(&self, x: u16, y: u16) -> Option<usize> {
+        let mut start = 0;
+        for (i, line) in self.cursor_pos.lines.iter().enumerate() {
+            let visible = !self.scrolling
+                || (self.first_visible_line..self.first_visible_line + SCROLL_WINDOW_LINES)
+                    .contains(&i);
+            if visible && line.y == y {
+                // 占两列的字符在任何一列上都能找到，组合用字符不占列，找到的是它前面的字符
+                let index = (0..line.length()).find(|&index| {
+                    let column = line.x + line.columns[index as usize];
+                    let column = column + self.cursor_pos.shift_at(i, index);
+                    (column..column + line.char_width(index)).contains(&x)
+                });
+                if let Some(index) = index {
+                    return Some(start + index as usize);
+                }
+            }
+            start += line.length() as usize;
+        }
+
+        None
+    }
+
     /// 显示一个原始文本
+    ///
+    /// 滚动显示时，光标所在的行不在屏幕上时不显示
     pub fn display_raw_text<T>(&mut self, text: &T) -> MaybeError
     where
         T: Display,
     {
-        write!(self.stdout, "{}", text)?;
+        if self.cursor_line_visible() {
+            write!(self.stdout, "{}", text)?;
+        }
 
         Ok(())
     }
 
+    /// 让终端响铃
+    pub fn bell(&mut self) -> MaybeError {
+        write!(self.stdout, "\x07")?;
+        self.flush()?;
+        Ok(())
+    }
+
     /// 隐藏光标
     pub fn hide_cursor(&mut self) -> MaybeError {
         write!(self.stdout, "{}", cursor::Hide)?;
@@ -404,7 +1257,12 @@ impl TypeingTui {
     }
 
     /// 显示光标
+    ///
+    /// 使用方块光标时终端的光标一直隐藏，直到退出
     pub fn show_cursor(&mut self) -> MaybeError {
+        if self.block_caret {
+            return Ok(());
+        }
         write!(self.stdout, "{}", cursor::Show)?;
         self.flush()?;
         Ok(())
@@ -422,23 +1280,135 @@ impl TypeingTui {
         Ok(())
     }
 
+    /// 从光标的位置开始显示还没有输入的文本 `rest`，光标不移动
+    ///
+    /// 只会显示到行尾为止。`highlight` 为 `true` 时反转文本的颜色，参见 [`Text::with_reverse`]
+    pub fn display_untyped_ahead(&mut self, rest: &str, highlight: bool) -> MaybeError {
+        let rest: String = rest
+            .chars()
+            .take(self.cursor_pos.remaining_in_line())
+            .collect();
+        let text = self.theme.untyped(Text::from(rest));
+        let text = if highlight { text.with_reverse() } else { text };
+        self.display_raw_text(&text)?;
+        self.move_to_cur_pos()?;
+
+        Ok(())
+    }
+
+    /// 在光标的位置用反转的颜色显示还没有输入的字符 `target`，光标不移动
+    ///
+    /// `ahead` 是从光标位置开始之前可能显示过方块的文本，先用正常的样式重新显示，
+    /// 清除光标向左移动之前显示的方块。光标向右移动时，之前的方块已经被输入的字符覆盖。
+    /// `highlight` 为 `true` 时光标所在的单词已经反转显示，方块改为不反转，以便区分
+    pub fn display_block_caret(
+        &mut self,
+        ahead: &str,
+        target: char,
+        highlight: bool,
+    ) -> MaybeError {
+        if !self.block_caret {
+            return Ok(());
+        }
+
+        self.display_untyped_ahead(ahead, false)?;
+        if highlight {
+            self.display_untyped_ahead(ahead.split(' ').next().unwrap_or(""), true)?;
+        }
+        let Some(((x, y), caret)) = block_caret(&self.cursor_pos, target, highlight, &self.theme)
+        else {
+            return Ok(());
+        };
+        write!(self.stdout, "{}", cursor::Goto(x, y))?;
+        self.display_raw_text(&caret)?;
+        self.move_to_cur_pos()?;
+
+        Ok(())
+    }
+
+    /// 在光标的位置显示输入的多余字符，光标移动到这些字符之后
+    ///
+    /// 这一行中光标之后的文本会向右移动，`rest` 是从光标位置开始还没有输入的文本，
+    /// 只会显示到行尾为止。`overflow` 为空时删除之前显示的多余字符
+    pub fn display_overflow(&mut self, overflow: &Text, rest: &str) -> MaybeError {
+        let old_width = self
+            .cursor_pos
+            .insertions
+            .iter()
+            .find(|&&(line, index, _)| {
+                (line, index) == (self.cursor_pos.cur_line, self.cursor_pos.cur_char_in_line)
+            })
+            .map_or(0, |&(_, _, width)| width);
+        let width = overflow.length() as u16;
+
+        self.cursor_pos.set_insertion(0);
+        let (x, y) = self.cursor_pos.cur_pos();
+        let rest: String = rest
+            .chars()
+            .take(self.cursor_pos.remaining_in_line())
+            .collect();
+        if self.cursor_line_visible() {
+            write!(
+                self.stdout,
+                "{}{}{}{}",
+                cursor::Goto(x, y),
+                overflow,
+                self.theme.untyped(Text::from(rest)),
+                // 覆盖之前更长的多余字符
+                " ".repeat(old_width.saturating_sub(width) as usize)
+            )?;
+        }
+
+        self.cursor_pos.set_insertion(width);
+        self.move_to_cur_pos()?;
+
+        Ok(())
+    }
+
     pub fn move_to_next_char(&mut self) -> MaybeError {
         let (x, y) = self.cursor_pos.next();
-        write!(self.stdout, "{}", cursor::Goto(x, y));
+        write!(self.stdout, "{}", cursor::Goto(x, y))?;
 
         Ok(())
     }
 
     pub fn move_to_prev_char(&mut self) -> MaybeError {
         let (x, y) = self.cursor_pos.prev();
-        write!(self.stdout, "{}", cursor::Goto(x, y));
+        write!(self.stdout, "{}", cursor::Goto(x, y))?;
 
         Ok(())
     }
 
+    /// 光标现在的位置，用于 [`TypeingTui::animate_caret`]
+    pub fn caret_pos(&self) -> (u16, u16) {
+        self.cursor_pos.cur_pos()
+    }
+
+    /// 从 `from` 平滑地把光标移动到现在的位置
+    ///
+    /// 只在启用了平滑移动，并且在同一行中移动了多个字符时才会经过中间的位置，
+    /// 总共最多等待几毫秒
+    pub fn animate_caret(&mut self, from: (u16, u16)) -> MaybeError {
+        let (x, y) = self.cursor_pos.cur_pos();
+        if self.smooth_caret && from.1 == y && from.0.abs_diff(x) > 1 {
+            for step in 1..SMOOTH_CARET_STEPS {
+                let offset = (x as i32 - from.0 as i32) * step as i32 / SMOOTH_CARET_STEPS as i32;
+                write!(
+                    self.stdout,
+                    "{}",
+                    cursor::Goto((from.0 as i32 + offset) as u16, y)
+                )?;
+                self.flush()?;
+                thread::sleep(SMOOTH_CARET_STEP_DELAY);
+            }
+        }
+
+        self.move_to_cur_pos()
+    }
+
     pub fn move_to_cur_pos(&mut self) -> MaybeError {
         let (x, y) = self.cursor_pos.cur_pos();
-        write!(self.stdout, "{}", cursor::Goto(x, y));
+        write!(self.stdout, "{}", cursor::Goto(x, y))?;
 
         Ok(())
     }
@@ -446,11 +1416,10 @@ impl TypeingTui {
     pub fn current_line(&self) -> usize {
         self.cursor_pos.cur_line
     }
-}
 
-impl Default for TypeingTui {
-    fn default() -> Self {
-        Self::new()
+    /// 光标是否在显示的单词的最后一行
+    pub fn on_last_line(&self) -> bool {
+        self.cursor_pos.cur_line + 1 >= self.cursor_pos.lines.len()
     }
 }
 
@@ -458,8 +1427,260 @@ impl Drop for TypeingTui {
     /// 重置终端
     /// 清空终端，将光标设置为不闪烁的块
     fn drop(&mut self) {
-        write!(self.stdout, "{}{}{}", clear::All, cursor::SteadyBlock, cursor::Goto(1,1))
-            .expect("Could not reset terminal while exiting");
+        write!(
+            self.stdout,
+            "{}{}{}",
+            clear::All,
+            cursor::SteadyBlock,
+            cursor::Goto(1, 1)
+        )
+        .expect("Could not reset terminal while exiting");
+        if self.block_caret {
+            write!(self.stdout, "{}", cursor::Show)
+                .expect("Could not reset terminal while exiting");
+        }
+        if self.mouse {
+            write!(self.stdout, "{}", DISABLE_MOUSE)
+                .expect("Could not reset terminal while exiting");
+        }
+        if self.theme.cursor.is_some() {
+            // OSC 112 恢复光标的默认颜色
+            write!(self.stdout, "\x1b]112\x07").expect("Could not reset terminal while exiting");
+        }
         self.flush().expect("Could not flush stdout while exiting");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn han_characters_are_two_columns_wide() {
+        assert_eq!(Text::from("中文").length(), 4);
+    }
+
+    #[test]
+    fn emoji_and_symbols_are_two_columns_wide() {
+        assert_eq!(display_width("🚀"), 2);
+        assert_eq!(display_width("✅"), 2);
+        assert_eq!(display_width("a🚀b"), 4);
+    }
+
+    #[test]
+    fn combining_marks_take_no_columns() {
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(Text::from("café").length(), 4);
+    }
+
+    #[test]
+    fn centered_lines_in_a_short_terminal() {
+        // 终端只有 4 行
+        assert_eq!(centered_top_row(1, 4), 2);
+        assert_eq!(centered_top_row(2, 4), 2);
+        assert_eq!(centered_top_row(5, 4), 1);
+        // 奇数和偶数行数都一样居中
+        assert_eq!(centered_top_row(1, 5), 3);
+        assert_eq!(centered_top_row(2, 5), 2);
+        assert_eq!(centered_top_row(5, 5), 1);
+        assert_eq!(centered_top_row(5, 0), 1);
+    }
+
+    #[test]
+    fn bottom_lines_in_a_short_terminal() {
+        let rows = |num_lines, height| -> Vec<u16> {
+            (0..num_lines)
+                .map(|line_no| bottom_row(line_no, num_lines, height))
+                .collect()
+        };
+
+        assert_eq!(rows(1, 10), [8]);
+        assert_eq!(rows(2, 10), [7, 8]);
+        assert_eq!(rows(5, 10), [4, 5, 6, 7, 8]);
+        // 放不下的行不会计算出负数或者 0
+        assert_eq!(rows(1, 1), [1]);
+        assert_eq!(rows(2, 2), [1, 1]);
+        assert_eq!(rows(5, 4), [1, 1, 1, 1, 2]);
+        assert_eq!(rows(5, 0), [1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn cursor_without_lines_stays_in_the_corner() {
+        let mut pos = CursorPos::new();
+
+        assert_eq!(pos.cur_pos(), (1, 1));
+        assert_eq!(pos.next(), (1, 1));
+        assert_eq!(pos.prev(), (1, 1));
+        assert_eq!(pos.remaining_in_line(), 0);
+    }
+
+    #[test]
+    fn cursor_moves_across_lines() {
+        let mut pos = CursorPos::new();
+        pos.lines.push(LinePos::new(10, 5, "ab"));
+        pos.lines.push(LinePos::new(11, 6, "c"));
+
+        assert_eq!(pos.next(), (11, 5));
+        assert_eq!(pos.next(), (11, 6));
+        // 最后一个字符之后不再移动
+        assert_eq!(pos.next(), (11, 6));
+        assert_eq!(pos.prev(), (11, 5));
+        assert_eq!(pos.prev(), (10, 5));
+        assert_eq!(pos.prev(), (10, 5));
+    }
+
+    #[test]
+    fn cursor_moves_by_columns_over_wide_characters() {
+        let mut pos = CursorPos::new();
+        pos.lines.push(LinePos::new(10, 5, "中文"));
+        pos.lines.push(LinePos::new(10, 6, "e\u{301}x"));
+
+        assert_eq!(pos.cur_pos(), (10, 5));
+        assert_eq!(pos.remaining_in_line(), 2);
+        // 每个汉字占两列
+        assert_eq!(pos.next(), (12, 5));
+        assert_eq!(pos.remaining_in_line(), 1);
+        // 按字符数换行，而不是按列数
+        assert_eq!(pos.next(), (10, 6));
+        // 组合用字符不占列，与下一个字符在同一列
+        assert_eq!(pos.next(), (11, 6));
+        assert_eq!(pos.next(), (11, 6));
+        assert_eq!(pos.prev(), (11, 6));
+        assert_eq!(pos.prev(), (10, 6));
+        assert_eq!(pos.prev(), (12, 5));
+        assert_eq!(pos.prev(), (10, 5));
+    }
+
+    #[test]
+    fn text_length_counts_characters_not_bytes() {
+        assert_eq!("café".len(), 5);
+        assert_eq!(Text::from("café").length(), 4);
+        assert_eq!(Text::from("привет").length(), 6);
+        assert_eq!(Text::from('é').length(), 1);
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn long_word_is_broken_across_lines() {
+        let long_word = "x".repeat(100);
+        let lines = layout_words(&words(&["ab", &long_word, "cd"]), 40, 10, true);
+
+        assert_eq!(
+            lines,
+            [
+                "ab ".to_string(),
+                "x".repeat(40),
+                "x".repeat(40),
+                "x".repeat(20) + " cd",
+            ]
+        );
+        assert!(lines.iter().all(|line| line.length() <= 40));
+        // 断开的单词的每个字符仍然只显示一次
+        assert_eq!(lines.concat(), format!("ab {} cd", long_word));
+    }
+
+    #[test]
+    fn long_word_is_kept_whole_without_break_long_words() {
+        let long_word = "x".repeat(100);
+        let lines = layout_words(&words(&["ab", &long_word, "cd"]), 40, 10, false);
+
+        assert_eq!(
+            lines,
+            ["ab ".to_string(), long_word + " ", "cd".to_string()]
+        );
+    }
+
+    #[test]
+    fn string_and_text_lengths_agree() {
+        for content in ["", "hello", "café", "中文", "e\u{301}", "a🚀b", "tab\there"] {
+            let text = Text::from(content);
+            assert_eq!(content.length(), text.length(), "{:?}", content);
+            assert_eq!(content.to_string().length(), text.length(), "{:?}", content);
+            // 格式不影响长度
+            assert_eq!(
+                text.with_color(color::Red).with_underline().length(),
+                content.length()
+            );
+        }
+    }
+
+    #[test]
+    fn reverse_wraps_the_styled_text() {
+        let text = Text::from("word")
+            .with_faint()
+            .with_color(color::Green)
+            .with_reverse();
+        let raw = text.raw_text();
+
+        assert!(raw.starts_with(&style::Invert.to_string()));
+        // 结束时只关闭反转，不会影响后面的文本，也不会清除里面的颜色
+        assert!(raw.ends_with(&style::NoInvert.to_string()));
+        assert!(raw.contains(&style::Faint.to_string()));
+        assert!(raw.contains(&color::Fg(color::Green).to_string()));
+        assert_eq!(text.text(), "word");
+        assert_eq!(text.length(), 4);
+    }
+
+    #[test]
+    fn block_caret_follows_the_cursor() {
+        let theme = Theme::default();
+        let invert = style::Invert.to_string();
+        let mut pos = CursorPos::new();
+        assert!(block_caret(&pos, 'a', false, &theme).is_none());
+
+        pos.lines.push(LinePos::new(10, 5, "abcdef"));
+        let (at, caret) = block_caret(&pos, 'a', false, &theme).unwrap();
+        assert_eq!(at, pos.cur_pos());
+        assert_eq!(at, (10, 5));
+        assert_eq!(caret.text(), "a");
+        assert!(caret.raw_text().contains(&invert));
+
+        pos.next();
+        pos.next();
+        assert_eq!(block_caret(&pos, 'c', false, &theme).unwrap().0, (12, 5));
+
+        // 多余字符插入到光标前面时，方块跟着向右移动
+        pos.set_insertion(3);
+        assert_eq!(block_caret(&pos, 'c', false, &theme).unwrap().0, (15, 5));
+
+        // 突出显示当前单词时方块不反转
+        let (_, caret) = block_caret(&pos, 'c', true, &theme).unwrap();
+        assert!(!caret.raw_text().contains(&invert));
+    }
+
+    #[test]
+    fn layout_for_several_widths() {
+        let words = words(&["ab", "cd", "ef"]);
+        let layout = |max_width| layout_words(&words, max_width, 10, false);
+
+        assert_eq!(layout(80), ["ab cd ef"]);
+        // 每个单词加上后面的空格正好占满宽度
+        assert_eq!(layout(9), ["ab cd ef"]);
+        assert_eq!(layout(8), ["ab cd ", "ef"]);
+        assert_eq!(layout(6), ["ab cd ", "ef"]);
+        assert_eq!(layout(5), ["ab ", "cd ", "ef"]);
+        // 比宽度更长的单词仍然占一行
+        assert_eq!(layout(1), ["ab ", "cd ", "ef"]);
+    }
+
+    #[test]
+    fn layout_limits_words_per_line() {
+        let words = words(&["ab", "cd", "ef", "gh", "ij"]);
+
+        assert_eq!(
+            layout_words(&words, 80, 2, false),
+            ["ab cd ", "ef gh ", "ij"]
+        );
+    }
+
+    #[test]
+    fn layout_measures_wide_characters_in_columns() {
+        let words = words(&["中文", "ab"]);
+
+        assert_eq!(layout_words(&words, 8, 10, false), ["中文 ab"]);
+        assert_eq!(layout_words(&words, 7, 10, false), ["中文 ", "ab"]);
+    }
+}
@@ -1,16 +1,56 @@
+use std::path::PathBuf;
+
 use clap::ValueEnum;
+#[cfg(feature = "builtin-wordlists")]
 use include_flate::flate;
+use serde::Deserialize;
 
+#[cfg(feature = "builtin-wordlists")]
 flate!(static TOP_250: str          from "src/word_lists/top250");
+#[cfg(feature = "builtin-wordlists")]
 flate!(static TOP_500: str          from "src/word_lists/top500");
+#[cfg(feature = "builtin-wordlists")]
 flate!(static TOP_1000: str         from "src/word_lists/top1000");
+#[cfg(feature = "builtin-wordlists")]
 flate!(static TOP_2500: str         from "src/word_lists/top2500");
+#[cfg(feature = "builtin-wordlists")]
 flate!(static TOP_5000: str         from "src/word_lists/top5000");
+#[cfg(feature = "builtin-wordlists")]
 flate!(static TOP_10000: str        from "src/word_lists/top10000");
+#[cfg(feature = "builtin-wordlists")]
 flate!(static TOP_25000: str        from "src/word_lists/top25000");
+#[cfg(feature = "builtin-wordlists")]
 flate!(static TOP_MISSPELLED: str   from "src/word_lists/commonly_misspelled");
+#[cfg(feature = "builtin-wordlists")]
+flate!(static QUOTES: str           from "src/word_lists/quotes");
+#[cfg(feature = "builtin-wordlists")]
+flate!(static SPANISH: str          from "src/word_lists/spanish");
+#[cfg(feature = "builtin-wordlists")]
+flate!(static GERMAN: str           from "src/word_lists/german");
+
+/// 操作系统内置单词列表的路径
+pub const OS_WORDLIST_PATH: &str = "/usr/share/dict/words";
+
+/// [`OS_WORDLIST_PATH`] 不存在时尝试的路径，一些较老的 Unix 系统把单词列表放在这里
+const OS_WORDLIST_FALLBACK_PATH: &str = "/usr/dict/words";
+
+/// 操作系统内置单词列表的实际路径
+///
+/// 依次查找 [`OS_WORDLIST_PATH`] 和 `/usr/dict/words`，都不存在时返回 `None`。
+/// 非 Unix 系统（例如 Windows）上没有这样的文件，总是返回 `None`
+pub fn os_wordlist_path() -> Option<PathBuf> {
+    if !cfg!(unix) {
+        return None;
+    }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, ValueEnum)]
+    [OS_WORDLIST_PATH, OS_WORDLIST_FALLBACK_PATH]
+        .into_iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum BuiltInWordlist {
     /// Source: [wordfrequency.info](https://www.wordfrequency.info/samples.asp) (top 60K lemmas sample).
     Top250,
@@ -41,6 +81,143 @@ pub enum BuiltInWordlist {
 
     /// The operating system's builtin word list.
     ///
-    /// See [`OS_WORDLIST_PATH`].
+    /// See [`os_wordlist_path`].
+    #[serde(rename = "os")]
     OS,
 }
+
+impl BuiltInWordlist {
+    /// 内置单词列表的内容
+    ///
+    /// [`BuiltInWordlist::OS`] 没有内置内容，返回 `None`
+    #[cfg(feature = "builtin-wordlists")]
+    pub fn contents(&self) -> Option<&'static str> {
+        match self {
+            Self::Top250 => Some(&TOP_250),
+            Self::Top500 => Some(&TOP_500),
+            Self::Top1000 => Some(&TOP_1000),
+            Self::Top2500 => Some(&TOP_2500),
+            Self::Top5000 => Some(&TOP_5000),
+            Self::Top10000 => Some(&TOP_10000),
+            Self::Top25000 => Some(&TOP_25000),
+            Self::CommonlyMisspelled => Some(&TOP_MISSPELLED),
+            Self::OS => None,
+        }
+    }
+
+    /// 编译时没有启用 `builtin-wordlists` 特性，没有任何内置的单词列表
+    #[cfg(not(feature = "builtin-wordlists"))]
+    pub fn contents(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// 单词列表的语言
+///
+/// 英语使用 `--wordlist` 选择的单词列表，其他语言使用各自内置的单词列表。
+///
+/// 添加一种新的语言：
+///
+/// 1. 把单词列表放到 `src/word_lists/` 中，每行一个单词，按照词频从高到低排列，
+///    这样 `--weighted` 才有意义。只有由 2 到 8 个字母组成的单词会被使用
+/// 2. 在这个文件的开头用 `flate!` 嵌入这个文件
+/// 3. 在这个枚举中添加一个变体，并在 [`Language::contents`] 中返回嵌入的单词列表
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Language {
+    English,
+    /// 占位的单词列表，只有一些最常用的单词
+    Spanish,
+    /// 占位的单词列表，只有一些最常用的单词
+    German,
+}
+
+impl Language {
+    /// 这种语言内置的单词列表
+    ///
+    /// 英语返回 `None`，使用 [`BuiltInWordlist`] 中的单词列表
+    #[cfg(feature = "builtin-wordlists")]
+    pub fn contents(&self) -> Option<&'static str> {
+        match self {
+            Self::English => None,
+            Self::Spanish => Some(&SPANISH),
+            Self::German => Some(&GERMAN),
+        }
+    }
+
+    /// 编译时没有启用 `builtin-wordlists` 特性，没有任何内置的单词列表
+    #[cfg(not(feature = "builtin-wordlists"))]
+    pub fn contents(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// 内置的名言，每行一句
+///
+/// 编译时没有启用 `builtin-wordlists` 特性时返回 `None`
+#[cfg(feature = "builtin-wordlists")]
+pub fn quotes() -> Option<&'static str> {
+    Some(&QUOTES)
+}
+
+#[cfg(not(feature = "builtin-wordlists"))]
+pub fn quotes() -> Option<&'static str> {
+    None
+}
+
+/// 所有嵌入的单词列表和名言，以及它们的名称
+///
+/// 编译时没有启用 `builtin-wordlists` 特性时为空
+#[cfg(feature = "builtin-wordlists")]
+pub fn embedded_lists() -> Vec<(&'static str, &'static str)> {
+    vec![
+        ("top250", &TOP_250),
+        ("top500", &TOP_500),
+        ("top1000", &TOP_1000),
+        ("top2500", &TOP_2500),
+        ("top5000", &TOP_5000),
+        ("top10000", &TOP_10000),
+        ("top25000", &TOP_25000),
+        ("commonly-misspelled", &TOP_MISSPELLED),
+        ("quotes", &QUOTES),
+        ("spanish", &SPANISH),
+        ("german", &GERMAN),
+    ]
+}
+
+#[cfg(not(feature = "builtin-wordlists"))]
+pub fn embedded_lists() -> Vec<(&'static str, &'static str)> {
+    Vec::new()
+}
+
+/// 解压之后的内容的 FNV-1a 哈希，用于确认不同版本中嵌入的单词列表是否相同
+///
+/// 只用于比较，不需要抵抗碰撞
+pub fn content_hash(contents: &str) -> u64 {
+    contents.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// 名言的长度
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuoteLength {
+    /// 不超过 10 个单词
+    Short,
+    /// 11 到 30 个单词
+    Medium,
+    /// 超过 30 个单词
+    Long,
+}
+
+impl QuoteLength {
+    /// 有 `num_words` 个单词的名言是否属于这个长度
+    pub fn contains(&self, num_words: usize) -> bool {
+        match self {
+            Self::Short => num_words <= 10,
+            Self::Medium => (11..=30).contains(&num_words),
+            Self::Long => num_words > 30,
+        }
+    }
+}